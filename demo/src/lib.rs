@@ -1,12 +1,22 @@
-use bevy::{app::Startup, utils::hashbrown::HashMap};
+use bevy::app::{Startup, Update};
 use nih_plug::prelude::*;
-use nih_plug_bevy::{create_bevy_editor, param_plugin::{NIHParamPlugin, NIHParams}, BevyState};
+use nih_plug_bevy::{
+    param_plugin::NIHParams,
+    redraw_plugin::{NIHRedrawConfig, RedrawMode},
+    telemetry::{telemetry_channel, TelemetryReceiver, TelemetrySender},
+    widgets::{
+        level_meter::{LevelMeterPlugin, LevelMeterSource},
+        oscilloscope::{OscilloscopePlugin, ScopeSource},
+        resize_handle::ResizeHandlePlugin,
+        spectrum_analyzer::SpectrumAnalyzerPlugin,
+    },
+    BevyEditorBuilder, BevyState,
+};
 use sickle_ui::SickleUiPlugin;
+use setup::{OutputMeter, OutputScope};
 use widgets::param_slider::{ParamSlider, ParamSliderPlugin};
 use std::sync::Arc;
 
-use bevy_baseview::DefaultBaseviewPlugins;
-
 mod setup;
 mod widgets;
 
@@ -19,12 +29,18 @@ pub struct Gain {
 
     /// Needed to normalize the peak meter's response based on the sample rate.
     peak_meter_decay_weight: f32,
-    /// The current data for the peak meter. This is stored as an [`Arc`] so we can share it between
-    /// the GUI and the audio processing parts. If you have more state to share, then it's a good
-    /// idea to put all of that in a struct behind a single `Arc`.
-    ///
-    /// This is stored as voltage gain.
-    peak_meter: Arc<AtomicF32>,
+    /// The current data for the peak meter, stored as voltage gain. This is published to the GUI
+    /// through `peak_meter_sender` at the end of every buffer. If you have more state to share, then
+    /// it's a good idea to put all of that in a struct and send that through a single channel.
+    peak_meter: f32,
+    peak_meter_sender: TelemetrySender<f32>,
+    /// Registered with the editor, which receives the peak meter as a `NIHTelemetry<f32>` resource.
+    peak_meter_receiver: TelemetryReceiver<f32>,
+    /// The output levels shown by the editor's level meter. The meter applies its own ballistics,
+    /// so the audio thread only has to pass along the processed samples.
+    output_meter: LevelMeterSource,
+    /// The last couple of output samples, shown by the editor's oscilloscope and spectrum analyzer.
+    output_scope: ScopeSource,
 }
 
 #[derive(Params)]
@@ -44,11 +60,17 @@ pub struct GainParams {
 
 impl Default for Gain {
     fn default() -> Self {
+        let (peak_meter_sender, peak_meter_receiver) = telemetry_channel(util::MINUS_INFINITY_DB);
+
         Self {
             params: Arc::new(GainParams::default()),
 
             peak_meter_decay_weight: 1.0,
-            peak_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            peak_meter: util::MINUS_INFINITY_DB,
+            peak_meter_sender,
+            peak_meter_receiver,
+            output_meter: LevelMeterSource::new(2),
+            output_scope: ScopeSource::new(2, 16384),
         }
     }
 }
@@ -109,19 +131,28 @@ impl Plugin for Gain {
 
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         let params = self.params.clone();
-        let peak_meter = self.peak_meter.clone();
-        create_bevy_editor(
-            self.params.editor_state.clone(),
-            move |app| {
-                let mut param_map = HashMap::new();
-                param_map.insert("gain".to_string(), params.gain.as_ptr());
-                app.add_plugins(DefaultBaseviewPlugins)
+        let output_meter = self.output_meter.clone();
+        let output_scope = self.output_scope.clone();
+        BevyEditorBuilder::new(self.params.editor_state.clone())
+            .with_telemetry(self.peak_meter_receiver.clone())
+            .build(move |app| {
+                app.add_plugins(nih_plug_bevy::default_plugins())
                     .add_plugins(SickleUiPlugin)
                     .add_plugins(ParamSliderPlugin)
-                    .insert_resource(NIHParams::new(param_map))
+                    .add_plugins(LevelMeterPlugin)
+                    .add_plugins(ResizeHandlePlugin)
+                    .add_plugins(OscilloscopePlugin)
+                    .add_plugins(SpectrumAnalyzerPlugin)
+                    .insert_resource(OutputMeter(output_meter.clone()))
+                    .insert_resource(OutputScope(output_scope.clone()))
+                    .insert_resource(NIHParams::from_params(params.clone()))
+                    .insert_resource(NIHRedrawConfig {
+                        mode: RedrawMode::Reactive,
+                        fps_cap: Some(60.0),
+                    })
                     .add_systems(Startup, setup::setup)
-            }
-        )
+                    .add_systems(Update, setup::update_peak_label)
+            })
     }
 
     fn initialize(
@@ -135,6 +166,7 @@ impl Plugin for Gain {
         self.peak_meter_decay_weight = 0.25f64
             .powf((buffer_config.sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip())
             as f32;
+        self.output_scope.set_sample_rate(buffer_config.sample_rate);
 
         true
     }
@@ -159,19 +191,21 @@ impl Plugin for Gain {
             // calculations that are only displayed on the GUI while the GUI is open
             if self.params.editor_state.is_open() {
                 amplitude = (amplitude / num_samples as f32).abs();
-                let current_peak_meter = self.peak_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_peak_meter = if amplitude > current_peak_meter {
+                self.peak_meter = if amplitude > self.peak_meter {
                     amplitude
                 } else {
-                    current_peak_meter * self.peak_meter_decay_weight
+                    self.peak_meter * self.peak_meter_decay_weight
                         + amplitude * (1.0 - self.peak_meter_decay_weight)
                 };
-
-                self.peak_meter
-                    .store(new_peak_meter, std::sync::atomic::Ordering::Relaxed)
             }
         }
 
+        if self.params.editor_state.is_open() {
+            self.peak_meter_sender.write(self.peak_meter);
+            self.output_meter.push_buffer(buffer);
+            self.output_scope.push_buffer(buffer);
+        }
+
         ProcessStatus::Normal
     }
 }
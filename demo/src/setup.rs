@@ -1,11 +1,35 @@
 use bevy::prelude::*;
-use nih_plug_bevy::param_plugin::NIHParams;
+use nih_plug_bevy::{
+    param_plugin::NIHParams,
+    telemetry::NIHTelemetry,
+    widgets::{
+        level_meter::{LevelMeter, LevelMeterBundle, LevelMeterSource},
+        oscilloscope::{Oscilloscope, OscilloscopeBundle, ScopeSource},
+        spectrum_analyzer::{SpectrumAnalyzer, SpectrumAnalyzerBundle},
+        resize_handle::{ResizeHandle, ResizeHandleBundle},
+    },
+};
 use sickle_ui::prelude::*;
 use crate::widgets::param_slider::{ParamSliderConfig, UiParamSliderExt};
 
+/// The plugin's output levels, fed from `process()`.
+#[derive(Resource)]
+pub struct OutputMeter(pub LevelMeterSource);
+
+/// The plugin's last couple of output samples, fed from `process()`. This feeds both the
+/// oscilloscope and the spectrum analyzer.
+#[derive(Resource)]
+pub struct OutputScope(pub ScopeSource);
+
+/// Shows the peak meter that the plugin publishes through its telemetry channel.
+#[derive(Component)]
+pub struct PeakLabel;
+
 pub fn setup(
     mut commands: Commands,
-    params: Res<NIHParams>
+    params: Res<NIHParams>,
+    output_meter: Res<OutputMeter>,
+    output_scope: Res<OutputScope>,
 ) {
     commands.spawn(Camera2dBundle::default());
 
@@ -18,7 +42,7 @@ pub fn setup(
                     100.0, 
                     true
                 ),
-                params.params.get("gain").unwrap().clone()
+                params.get("gain").unwrap()
             );
         })
         .style()
@@ -36,10 +60,48 @@ pub fn setup(
         })
         .style()
         .width(Val::Percent(60.));
+        column.row(|row|{
+            row.spawn((
+                TextBundle::from_section("", TextStyle { font_size: 14., ..default() }),
+                PeakLabel,
+            ));
+        });
+        column.row(|row|{
+            row.spawn(LevelMeterBundle::new(LevelMeter::new(output_meter.0.clone())));
+            row.spawn(OscilloscopeBundle::new(Oscilloscope::new(output_scope.0.clone())));
+            row.spawn(SpectrumAnalyzerBundle::new(
+                SpectrumAnalyzer::new(output_scope.0.clone()).with_slope(4.5)
+            ));
+        });
     })
     .style()
     .width(Val::Percent(100.))
     .height(Val::Percent(100.))
     .justify_content(JustifyContent::Center)
     .align_items(AlignItems::Center);
-}
\ No newline at end of file
+
+    commands.spawn(ResizeHandleBundle::new(
+        ResizeHandle::new()
+            .with_min_size(384, 256)
+            .with_max_size(1536, 1024)
+    ));
+}
+
+/// The telemetry resource only changes when the audio thread published a new peak value.
+pub fn update_peak_label(
+    peak_meter: Res<NIHTelemetry<f32>>,
+    mut labels: Query<&mut Text, With<PeakLabel>>,
+) {
+    if !peak_meter.is_changed() {
+        return;
+    }
+
+    let peak_db = nih_plug::util::gain_to_db(*peak_meter.value());
+    for mut text in &mut labels {
+        text.sections[0].value = if peak_db <= nih_plug::util::MINUS_INFINITY_DB {
+            "Peak: -inf dB".to_string()
+        } else {
+            format!("Peak: {peak_db:.1} dB")
+        };
+    }
+}
@@ -22,6 +22,7 @@ impl Plugin for ParamSliderPlugin {
                     update_slider_on_scroll.after(ScrollableUpdate),
                     update_slider_on_drag.after(DraggableUpdate),
                     update_slider_on_bar_change,
+                    update_slider_on_param_change,
                     update_slider_handle,
                     update_slider_readout,
                 )
@@ -90,7 +91,7 @@ fn update_slider_on_drag(
         }
 
         if draggable.state == DragState::DragCanceled {
-            if let Some(base_ratio) = slider.base_ratio {
+            if let Some(base_ratio) = slider.base_ratio.take() {
                 slider.ratio = base_ratio;
                 param_evt.send(ParamEvent::set(slider_id, slider.ratio));
                 param_evt.send(ParamEvent::end(slider_id));
@@ -103,40 +104,46 @@ fn update_slider_on_drag(
             param_evt.send(ParamEvent::begin(slider_id));
         }
 
-        let Ok(slider_bar) = q_node.get(slider.bar_container) else {
-            continue;
-        };
-        let Some(diff) = draggable.diff else {
-            continue;
-        };
-
-        let axis = &slider.config.axis;
-        let fraction = match axis {
-            ParamSliderAxis::Horizontal => {
-                let width = slider_bar.size().x - node.size().x;
-                if diff.x == 0. || width == 0. {
-                    continue;
-                }
-                diff.x / width
-            }
-            ParamSliderAxis::Vertical => {
-                let height = slider_bar.size().y - node.size().y;
-                if diff.y == 0. || height == 0. {
-                    continue;
-                }
-                -diff.y / height
-            }
-        };
-
-        slider.ratio = (slider.ratio + fraction).clamp(0., 1.);
-        param_evt.send(ParamEvent::set(slider_id, slider.ratio));
+        let fraction = q_node
+            .get(slider.bar_container)
+            .ok()
+            .zip(draggable.diff)
+            .and_then(|(slider_bar, diff)| {
+                drag_fraction(&slider.config.axis, slider_bar, node, diff)
+            });
+        if let Some(fraction) = fraction {
+            slider.ratio = (slider.ratio + fraction).clamp(0., 1.);
+            param_evt.send(ParamEvent::set(slider_id, slider.ratio));
+        }
 
+        // This has to happen even if the last event didn't move the handle, or the gesture would
+        // never end and the slider would stop following the host
         if draggable.state == DragState::DragEnd {
+            slider.base_ratio = None;
             param_evt.send(ParamEvent::end(slider_id));
         }
     }
 }
 
+/// How far the handle was dragged along the slider's axis, as a fraction of the slider's length.
+fn drag_fraction(
+    axis: &ParamSliderAxis,
+    slider_bar: &Node,
+    handle: &Node,
+    diff: Vec2,
+) -> Option<f32> {
+    match axis {
+        ParamSliderAxis::Horizontal => {
+            let width = slider_bar.size().x - handle.size().x;
+            (diff.x != 0. && width != 0.).then(|| diff.x / width)
+        }
+        ParamSliderAxis::Vertical => {
+            let height = slider_bar.size().y - handle.size().y;
+            (diff.y != 0. && height != 0.).then(|| -diff.y / height)
+        }
+    }
+}
+
 fn update_slider_on_bar_change(
     q_slider_bars: Query<&ParamSliderBar, Changed<Node>>,
     mut q_slider: Query<&mut ParamSlider>,
@@ -150,6 +157,22 @@ fn update_slider_on_bar_change(
     }
 }
 
+fn update_slider_on_param_change(
+    mut q_slider: Query<(&NIHParam, &mut ParamSlider), Changed<NIHParam>>,
+) {
+    for (nih_param, mut slider) in &mut q_slider {
+        // The slider is the one changing the parameter while it's being dragged, resyncing here
+        // would make the handle jump back to a value the host hasn't caught up with yet
+        if slider.base_ratio.is_some() {
+            continue;
+        }
+
+        if slider.ratio != nih_param.normalized_value() {
+            slider.ratio = nih_param.normalized_value();
+        }
+    }
+}
+
 fn update_slider_handle(
     q_slider: Query<&ParamSlider, Or<(Changed<ParamSlider>, Changed<Node>)>>,
     q_node: Query<&Node>,
@@ -183,15 +206,18 @@ fn update_slider_handle(
     }
 }
 
-fn update_slider_readout(q_slider: Query<&ParamSlider, Changed<ParamSlider>>, mut commands: Commands) {
-    for slider in &q_slider {
+fn update_slider_readout(
+    q_slider: Query<(&ParamSlider, &NIHParam), Changed<ParamSlider>>,
+    mut commands: Commands,
+) {
+    for (slider, nih_param) in &q_slider {
         if !slider.config.show_current {
             continue;
         }
 
         commands
             .entity(slider.readout)
-            .update_text(format!("{:.1}", slider.value()));
+            .update_text(nih_param.normalized_value_to_string(slider.ratio, true));
     }
 }
 
@@ -597,7 +623,7 @@ pub trait UiParamSliderExt {
 impl UiParamSliderExt for UiBuilder<'_, Entity> {
     fn param_slider(&mut self, config: ParamSliderConfig, param: ParamPtr) -> UiBuilder<Entity> {
         let mut slider = ParamSlider {
-            ratio: unsafe { param.default_normalized_value() },
+            ratio: unsafe { param.unmodulated_normalized_value() },
             config: config.clone(),
             ..default()
         };
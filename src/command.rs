@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use crossbeam::queue::ArrayQueue;
+
+/// Create a queue for sending messages that aren't parameter changes, like "clear the delay
+/// buffer" or "load this file", from the editor to the audio thread. The [`CommandSender`] is
+/// registered with [`BevyEditorBuilder::with_commands()`][crate::BevyEditorBuilder::with_commands()],
+/// or with a [`NIHCommandPlugin`], and shows up as a [`NIHCommands<T>`] resource in the editor. The
+/// plugin keeps the [`CommandReceiver`] and drains it from `process()` without blocking.
+///
+/// At most `capacity` commands can be waiting at once. Sending more than that fails until the audio
+/// thread catches up.
+pub fn command_channel<T: Send + 'static>(capacity: usize) -> (CommandSender<T>, CommandReceiver<T>) {
+    let queue = Arc::new(ArrayQueue::new(capacity.max(1)));
+
+    (CommandSender(queue.clone()), CommandReceiver(queue))
+}
+
+/// The editor's end of a [`command_channel()`]. All clones feed the same [`CommandReceiver`], so
+/// the plugin can keep one around and hand a clone to every editor instance it opens.
+pub struct CommandSender<T>(Arc<ArrayQueue<T>>);
+
+impl<T> Clone for CommandSender<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> CommandSender<T> {
+    /// Queue a command for the audio thread. The command is handed back if the queue is full.
+    pub fn send(&self, command: T) -> Result<(), T> {
+        self.0.push(command)
+    }
+}
+
+/// The audio thread's end of a [`command_channel()`].
+pub struct CommandReceiver<T>(Arc<ArrayQueue<T>>);
+
+impl<T> CommandReceiver<T> {
+    /// The oldest command that hasn't been received yet, if any.
+    pub fn try_recv(&self) -> Option<T> {
+        self.0.pop()
+    }
+
+    /// Receive all commands that are currently waiting. Commands sent while iterating are received
+    /// as well. Keep in mind that dropping a command that owns heap memory, like a file path,
+    /// deallocates on the audio thread.
+    pub fn drain(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.0.pop())
+    }
+}
+
+//<========== Resources ==========>//
+
+/// Sends commands to the audio thread through a [`command_channel()`].
+#[derive(Resource)]
+pub struct NIHCommands<T: Send + 'static> {
+    sender: CommandSender<T>,
+}
+
+impl<T: Send + 'static> NIHCommands<T> {
+    /// Queue a command for the audio thread. If the queue is full the command is dropped with a
+    /// warning and `false` is returned.
+    pub fn send(&self, command: T) -> bool {
+        if self.sender.send(command).is_err() {
+            warn!("The command queue is full, the audio thread isn't keeping up");
+            return false;
+        }
+
+        true
+    }
+
+    pub fn sender(&self) -> &CommandSender<T> {
+        &self.sender
+    }
+}
+
+//<========== Plugin ==========>//
+
+/// Makes the editor's end of a [`command_channel()`] available as a [`NIHCommands<T>`] resource.
+pub struct NIHCommandPlugin<T> {
+    sender: CommandSender<T>,
+}
+
+impl<T> NIHCommandPlugin<T> {
+    pub fn new(sender: CommandSender<T>) -> Self {
+        Self { sender }
+    }
+}
+
+impl<T: Send + 'static> Plugin for NIHCommandPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NIHCommands {
+            sender: self.sender.clone(),
+        });
+    }
+}
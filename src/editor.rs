@@ -1,21 +1,98 @@
-use std::sync::{atomic::Ordering, Arc};
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
 
-use baseview::{gl::GlConfig, Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
-use bevy::{app::App, render::view::window};
-use crossbeam::atomic::AtomicCell;
-use nih_plug::editor::Editor;
+#[cfg(feature = "opengl")]
+use baseview::gl::GlConfig;
+use baseview::{Size, WindowHandle, WindowOpenOptions, WindowScalePolicy};
+use bevy::{
+    app::App,
+    render::{settings::RenderCreation, RenderPlugin},
+};
+use crossbeam::{atomic::AtomicCell, channel::{Receiver, Sender}};
+use nih_plug::{editor::Editor, prelude::GuiContext};
 
-use crate::{param_plugin::{NIHContext, NIHCurrentParam, NIHParamPlugin}, BevyState};
+use crate::{
+    lifecycle_plugin::{NIHEditorClosing, NIHLifecyclePlugin},
+    param_plugin::{
+        param_id_hash, HostParamUpdate, NIHContext, NIHGestures, NIHHostUpdates, NIHParamPlugin,
+        OpenGestures,
+    },
+    redraw_plugin::NIHRedrawPlugin,
+    window_plugin::{NIHBevyState, NIHScaleFactor, NIHWindowPlugin},
+    BevyState,
+};
+
+/// The number of host parameter notifications that can be queued up between two frames. Anything
+/// beyond that is dropped in favor of a full resync.
+pub(crate) const PARAM_UPDATE_QUEUE_CAPACITY: usize = 4096;
+
+/// The window and graphics options set through [`BevyEditorBuilder`][crate::BevyEditorBuilder].
+#[derive(Debug, Clone)]
+#[cfg_attr(any(not(feature = "opengl"), feature = "wgpu"), allow(dead_code))]
+pub(crate) struct WindowConfig {
+    pub(crate) title: String,
+    pub(crate) samples: Option<u8>,
+    pub(crate) vsync: bool,
+    pub(crate) srgb: bool,
+    pub(crate) depth_bits: u8,
+    pub(crate) stencil_bits: u8,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("bevy window"),
+            samples: None,
+            vsync: true,
+            srgb: true,
+            depth_bits: 24,
+            stencil_bits: 8,
+        }
+    }
+}
+
+#[cfg(feature = "opengl")]
+impl WindowConfig {
+    fn gl_config(&self) -> GlConfig {
+        GlConfig {
+            version: (3, 2),
+            red_bits: 8,
+            blue_bits: 8,
+            green_bits: 8,
+            alpha_bits: 8,
+            depth_bits: self.depth_bits,
+            stencil_bits: self.stencil_bits,
+            samples: self.samples,
+            srgb: self.srgb,
+            double_buffer: true,
+            vsync: self.vsync,
+            ..Default::default()
+        }
+    }
+}
 
 pub(crate) struct BevyEditor {
     pub(crate) bevy_state: Arc<BevyState>,
+    pub(crate) window_config: WindowConfig,
 
+    /// Plugins registered through the builder, like telemetry and command channels. Applied right
+    /// before `build`.
+    pub(crate) extensions: Vec<Arc<dyn Fn(&mut App) + 'static + Send + Sync>>,
     /// The user's build function. Applied once at the start of the application.
     pub(crate) build: Arc<dyn Fn(&mut App) -> &mut App + 'static + Send + Sync>,
 
     /// The scaling factor reported by the host, if any. On macOS this will never be set and we
     /// should use the system scaling factor instead.
     pub(crate) scaling_factor: AtomicCell<Option<f32>>,
+    /// A scale factor the host has set while the editor was open, picked up by the Bevy app on its
+    /// next frame.
+    pub(crate) pending_scaling_factor: Arc<AtomicCell<Option<f32>>>,
+
+    /// Forwards the host's parameter notifications to the Bevy app, which drains
+    /// `param_updates_receiver` once per frame.
+    pub(crate) param_updates_sender: Sender<HostParamUpdate>,
+    pub(crate) param_updates_receiver: Receiver<HostParamUpdate>,
+    /// Set when all parameter values need to be re-read by the Bevy app on its next frame.
+    pub(crate) param_values_changed: Arc<AtomicBool>,
 }
 
 impl Editor for BevyEditor {
@@ -25,14 +102,32 @@ impl Editor for BevyEditor {
         context: Arc<dyn nih_plug::prelude::GuiContext>,
     ) -> Box<dyn std::any::Any + Send> {
         let build = self.build.clone();
+        let extensions = self.extensions.clone();
+
+        // Anything left over from a previous session is outdated, the parameters' current values
+        // are read again when the widgets get spawned
+        let param_updates = self.param_updates_receiver.clone();
+        while param_updates.try_recv().is_ok() {}
+        self.param_values_changed.store(false, Ordering::Release);
+        let param_values_changed = self.param_values_changed.clone();
+        let open_gestures = OpenGestures::default();
+        let closing = Arc::new(AtomicBool::new(false));
+        self.bevy_state.open.store(true, Ordering::Release);
 
-        let (unscaled_width, unscaled_height) = self.bevy_state.size();
+        self.bevy_state.size.store(self.bevy_state.clamp_size(self.bevy_state.size()));
+        let (unscaled_width, unscaled_height) = self.bevy_state.scaled_size();
         let scaling_factor = self.scaling_factor.load();
+        self.pending_scaling_factor.store(None);
+        let pending_scaling_factor = self.pending_scaling_factor.clone();
 
+        let bevy_state = self.bevy_state.clone();
+        let handle_context = context.clone();
+        let handle_gestures = open_gestures.clone();
+        let handle_closing = closing.clone();
         let window = bevy_baseview::open_parented(
             parent, 
             WindowOpenOptions {
-                title: String::from("bevy window"),
+                title: self.window_config.title.clone(),
                 // Baseview should be doing the DPI scaling for us
                 size: Size::new(unscaled_width as f64, unscaled_height as f64),
                 // NOTE: For some reason passing 1.0 here causes the UI to be scaled on macOS but
@@ -40,63 +135,140 @@ impl Editor for BevyEditor {
                 scale: scaling_factor
                     .map(|factor| WindowScalePolicy::ScaleFactor(factor as f64))
                     .unwrap_or(WindowScalePolicy::SystemScaleFactor),
+                // With the wgpu backend Bevy creates its own surface for the window, so there's no
+                // need for an OpenGL context
                 #[cfg(feature = "opengl")]
-                gl_config: Some(GlConfig {
-                    version: (3, 2),
-                    red_bits: 8,
-                    blue_bits: 8,
-                    green_bits: 8,
-                    alpha_bits: 8,
-                    depth_bits: 24,
-                    stencil_bits: 8,
-                    samples: None,
-                    srgb: true,
-                    double_buffer: true,
-                    vsync: true,
-                    ..Default::default()
-                }),
+                gl_config: if crate::uses_wgpu() {
+                    None
+                } else {
+                    Some(self.window_config.gl_config())
+                },
             },
             move |app| {
                 app.insert_resource(NIHContext::new(context.clone()))
-                    .init_resource::<NIHCurrentParam>()
-                    .add_plugins(NIHParamPlugin);
-                build(app)
+                    .insert_resource(NIHHostUpdates::new(
+                        param_updates.clone(),
+                        param_values_changed.clone(),
+                    ))
+                    .insert_resource(NIHGestures::new(open_gestures.clone()))
+                    .insert_resource(NIHBevyState::new(bevy_state.clone()))
+                    .insert_resource(NIHScaleFactor::new(pending_scaling_factor.clone()))
+                    .insert_resource(NIHEditorClosing::new(closing.clone()))
+                    .add_plugins(NIHParamPlugin)
+                    .add_plugins(NIHWindowPlugin)
+                    .add_plugins(NIHRedrawPlugin)
+                    .add_plugins(NIHLifecyclePlugin);
+                for extension in &extensions {
+                    extension(app);
+                }
+                let app = build(app);
+                check_render_backend(app);
+                app
             }
         );
 
         Box::new(BevyEditorHandle {
             bevy_state: self.bevy_state.clone(),
+            context: handle_context,
+            open_gestures: handle_gestures,
+            closing: handle_closing,
             window,
         })
     }
 
     fn size(&self) -> (u32, u32) {
-        self.bevy_state.size()
+        self.bevy_state.scaled_size()
     }
 
     fn set_scale_factor(&self, factor: f32) -> bool {
+        // While the editor is open, the Bevy app applies the new factor on its next frame
+        self.scaling_factor.store(Some(factor));
         if self.bevy_state.is_open() {
-            return false;
+            self.pending_scaling_factor.store(Some(factor));
         }
 
-        self.scaling_factor.store(Some(factor));
         true
     }
 
-    // For the moment we redraw every frame :p
-    // Potential TODO in the future to optimize the performance
-    // Then again Knuth said that "Premature optimization is the root of all evil"
-    // But my code will never mature. It'll stay young forever -- eternal and unoptimized.
-    fn param_value_changed(&self, _id: &str, _normalized_value: f32) {}
+    // These notifications also wake up the editor when it only redraws on demand, see
+    // `NIHRedrawPlugin`. They can be called from the audio thread, so they must not allocate.
+    fn param_value_changed(&self, id: &str, normalized_value: f32) {
+        if !self.bevy_state.is_open() {
+            return;
+        }
 
-    fn param_modulation_changed(&self, _id: &str, _modulation_offset: f32) {}
+        // If the queue is full the app isn't keeping up, so we'll just have it re-read everything
+        if self
+            .param_updates_sender
+            .try_send(HostParamUpdate::Value {
+                id_hash: param_id_hash(id),
+                normalized_value,
+            })
+            .is_err()
+        {
+            self.param_values_changed.store(true, Ordering::Release);
+        }
+    }
+
+    fn param_modulation_changed(&self, id: &str, modulation_offset: f32) {
+        if !self.bevy_state.is_open() {
+            return;
+        }
+
+        if self
+            .param_updates_sender
+            .try_send(HostParamUpdate::Modulation {
+                id_hash: param_id_hash(id),
+                modulation_offset,
+            })
+            .is_err()
+        {
+            self.param_values_changed.store(true, Ordering::Release);
+        }
+    }
+
+    fn param_values_changed(&self) {
+        if self.bevy_state.is_open() {
+            self.param_values_changed.store(true, Ordering::Release);
+        }
+    }
+}
+
+/// Warn when the app's render plugin doesn't use the backend selected through the crate's features,
+/// since the editor's window is only set up for that backend. See [`crate::default_plugins()`].
+/// Plain `DefaultBaseviewPlugins` work fine with the OpenGL backend, so this only checks apps built
+/// with the `wgpu` feature.
+fn check_render_backend(app: &App) {
+    if !cfg!(feature = "wgpu") {
+        return;
+    }
+
+    let expected = crate::render_backends();
+    for plugin in app.get_added_plugins::<RenderPlugin>() {
+        let RenderCreation::Automatic(settings) = &plugin.render_creation else {
+            continue;
+        };
 
-    fn param_values_changed(&self) {}
+        if settings.backends != Some(expected) {
+            nih_plug::nih_warn!(
+                "The editor's render plugin uses the {:?} backends instead of {:?}, use \
+                 nih_plug_bevy::default_plugins() or nih_plug_bevy::render_plugin() to set it up",
+                settings.backends,
+                expected
+            );
+        }
+    }
 }
 
 /// The window handle used for [`BevyEditor`].
 struct BevyEditorHandle {
     bevy_state: Arc<BevyState>,
+    context: Arc<dyn GuiContext>,
+    /// The gestures the Bevy app hasn't ended yet. These are ended when the editor closes so the
+    /// host doesn't consider those parameters to be touched forever.
+    open_gestures: OpenGestures,
+    /// Lets the Bevy app know that it's about to be closed, see `EditorClose`.
+    closing: Arc<AtomicBool>,
     window: WindowHandle,
 }
 
@@ -107,6 +279,8 @@ unsafe impl Send for BevyEditorHandle {}
 impl Drop for BevyEditorHandle {
     fn drop(&mut self) {
         self.bevy_state.open.store(false, Ordering::Release);
+        self.closing.store(true, Ordering::Release);
+        self.open_gestures.end_all(&*self.context);
         // XXX: This should automatically happen when the handle gets dropped, but apparently not
         self.window.close();
     }
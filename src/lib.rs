@@ -1,15 +1,35 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use bevy::app::App;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use bevy::app::{App, PluginGroup, PluginGroupBuilder};
+use bevy_baseview::DefaultBaseviewPlugins;
+use bevy::render::{
+    settings::{Backends, RenderCreation, WgpuSettings},
+    RenderPlugin,
+};
 use crossbeam::atomic::AtomicCell;
 use nih_plug::editor::Editor;
-use serde::{Serialize, Deserialize};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::command::{CommandSender, NIHCommandPlugin};
+use crate::telemetry::{NIHTelemetryPlugin, TelemetryReceiver};
 
 use nih_plug::params::persist::PersistentField;
 
 mod editor;
+mod persist;
+pub mod command;
+pub mod lifecycle_plugin;
 pub mod param_plugin;
+pub mod redraw_plugin;
+pub mod telemetry;
+pub mod ui_state_plugin;
+pub mod widgets;
+pub mod window_plugin;
+
+pub use baseview::WindowScalePolicy;
 
+/// Create a Bevy editor with the default window and graphics settings. See [`BevyEditorBuilder`] to
+/// change those.
 pub fn create_bevy_editor<B>(
     bevy_state: Arc<BevyState>,
     build: B,
@@ -17,31 +37,248 @@ pub fn create_bevy_editor<B>(
 where
     B: Fn(&mut App) -> &mut App + 'static + Send + Sync,
 {
-    Some(Box::new(editor::BevyEditor {
-        bevy_state,
-        build: Arc::new(build),
-
-        // TODO: We can't get the size of the window when baseview does its own scaling, so if the
-        //       host does not set a scale factor on Windows or Linux we should just use a factor of
-        //       1. That may make the GUI tiny but it also prevents it from getting cut off.
-        #[cfg(target_os = "macos")]
-        scaling_factor: AtomicCell::new(None),
-        #[cfg(not(target_os = "macos"))]
-        scaling_factor: AtomicCell::new(Some(1.0)),
-    }))
+    BevyEditorBuilder::new(bevy_state).build(build)
+}
+
+/// Bevy's default plugins for the editor's window, with the render plugin from [`render_plugin()`].
+/// Add these in the editor's build function. With the `wgpu` feature the editor warns when it's
+/// opened with a render plugin that doesn't use the selected backend, since Bevy's default would
+/// pick one without knowing whether the window has an OpenGL context.
+pub fn default_plugins() -> PluginGroupBuilder {
+    DefaultBaseviewPlugins.build().set(render_plugin())
+}
+
+/// A [`RenderPlugin`] that makes Bevy use the backend selected through this crate's features: the
+/// window's OpenGL context with the `opengl` feature, or a native wgpu surface (Vulkan, Metal or
+/// DX12) with the `wgpu` feature. When the `wgpu` feature is enabled but there's no native adapter,
+/// this uses OpenGL if the `opengl` feature is enabled as well. [`default_plugins()`] already uses
+/// this.
+pub fn render_plugin() -> RenderPlugin {
+    RenderPlugin {
+        render_creation: RenderCreation::Automatic(WgpuSettings {
+            backends: Some(render_backends()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub(crate) fn render_backends() -> Backends {
+    if uses_wgpu() {
+        Backends::PRIMARY
+    } else {
+        Backends::GL
+    }
+}
+
+/// Whether the editor renders on a native wgpu surface. With the `wgpu` feature this checks once
+/// whether any Vulkan, Metal or DX12 adapter exists, since Bevy's render plugin panics without one.
+pub(crate) fn uses_wgpu() -> bool {
+    static ADAPTER_AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+    cfg!(feature = "wgpu") && *ADAPTER_AVAILABLE.get_or_init(native_adapter_available)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg(feature = "wgpu")]
+fn native_adapter_available() -> bool {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::PRIMARY,
+        ..Default::default()
+    });
+    let available = !instance.enumerate_adapters(wgpu::Backends::PRIMARY).is_empty();
+    if !available && cfg!(feature = "opengl") {
+        nih_plug::nih_warn!(
+            "No Vulkan, Metal or DX12 adapter is available, falling back to OpenGL"
+        );
+    }
+
+    available
+}
+
+#[cfg(not(feature = "wgpu"))]
+fn native_adapter_available() -> bool {
+    false
+}
+
+/// Configures the editor's window before creating it with [`build()`][Self::build()].
+pub struct BevyEditorBuilder {
+    bevy_state: Arc<BevyState>,
+    window_config: editor::WindowConfig,
+    scaling_factor: Option<f32>,
+    /// Applied to the Bevy app before the user's build function, see
+    /// [`with_telemetry()`][Self::with_telemetry()] and [`with_commands()`][Self::with_commands()].
+    extensions: Vec<Arc<dyn Fn(&mut App) + 'static + Send + Sync>>,
+}
+
+impl BevyEditorBuilder {
+    pub fn new(bevy_state: Arc<BevyState>) -> Self {
+        Self {
+            bevy_state,
+            window_config: editor::WindowConfig::default(),
+
+            // TODO: We can't get the size of the window when baseview does its own scaling, so if the
+            //       host does not set a scale factor on Windows or Linux we should just use a factor of
+            //       1. That may make the GUI tiny but it also prevents it from getting cut off.
+            #[cfg(target_os = "macos")]
+            scaling_factor: None,
+            #[cfg(not(target_os = "macos"))]
+            scaling_factor: Some(1.0),
+            extensions: Vec::new(),
+        }
+    }
+
+    /// The window's title. Defaults to `"bevy window"`.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.window_config.title = title.into();
+        self
+    }
+
+    /// The number of MSAA samples, or `None` to disable multisampling. Defaults to `None`.
+    pub fn with_samples(mut self, samples: Option<u8>) -> Self {
+        self.window_config.samples = samples;
+        self
+    }
+
+    /// Whether to synchronize buffer swaps with the display's refresh rate. Defaults to `true`.
+    pub fn with_vsync(mut self, vsync: bool) -> Self {
+        self.window_config.vsync = vsync;
+        self
+    }
+
+    /// Whether to request an sRGB framebuffer. Defaults to `true`.
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.window_config.srgb = srgb;
+        self
+    }
+
+    /// The depth buffer's size in bits. Defaults to 24.
+    pub fn with_depth_bits(mut self, depth_bits: u8) -> Self {
+        self.window_config.depth_bits = depth_bits;
+        self
+    }
+
+    /// The stencil buffer's size in bits. Defaults to 8.
+    pub fn with_stencil_bits(mut self, stencil_bits: u8) -> Self {
+        self.window_config.stencil_bits = stencil_bits;
+        self
+    }
+
+    /// The scale policy used until the host sets a scale factor. Defaults to the system scale
+    /// factor on macOS, and to a factor of 1 everywhere else.
+    pub fn with_scale_policy(mut self, policy: WindowScalePolicy) -> Self {
+        self.scaling_factor = match policy {
+            WindowScalePolicy::ScaleFactor(factor) => Some(factor as f32),
+            WindowScalePolicy::SystemScaleFactor => None,
+        };
+        self
+    }
+
+    /// Make the values sent through a [`telemetry_channel()`][telemetry::telemetry_channel()]
+    /// available to the editor as a [`NIHTelemetry<T>`][telemetry::NIHTelemetry] resource. There
+    /// is one resource per type, so this can be called once for every value type. Put everything
+    /// that belongs together in a single struct, or wrap channels of the same type in newtypes.
+    pub fn with_telemetry<T>(mut self, receiver: TelemetryReceiver<T>) -> Self
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.extensions.push(Arc::new(move |app: &mut App| {
+            app.add_plugins(NIHTelemetryPlugin::new(receiver.clone()));
+        }));
+        self
+    }
+
+    /// Make the editor's end of a [`command_channel()`][command::command_channel()] available as a
+    /// [`NIHCommands<T>`][command::NIHCommands] resource. This can be called once for every
+    /// command type.
+    pub fn with_commands<T>(mut self, sender: CommandSender<T>) -> Self
+    where
+        T: Send + 'static,
+    {
+        self.extensions.push(Arc::new(move |app: &mut App| {
+            app.add_plugins(NIHCommandPlugin::new(sender.clone()));
+        }));
+        self
+    }
+
+    /// Create the editor. `build` is applied to the Bevy app every time the editor is opened.
+    pub fn build<B>(self, build: B) -> Option<Box<dyn Editor>>
+    where
+        B: Fn(&mut App) -> &mut App + 'static + Send + Sync,
+    {
+        // The plugin can still be used without a GUI
+        if !cfg!(any(feature = "opengl", feature = "wgpu")) {
+            nih_plug::nih_error!(
+                "nih_plug_bevy was compiled without the 'opengl' and 'wgpu' features, the editor \
+                 is disabled"
+            );
+            return None;
+        }
+        if !cfg!(feature = "opengl") && !uses_wgpu() {
+            nih_plug::nih_error!(
+                "No Vulkan, Metal or DX12 adapter is available and nih_plug_bevy was compiled \
+                 without the 'opengl' feature, the editor is disabled"
+            );
+            return None;
+        }
+
+        let (param_updates_sender, param_updates_receiver) =
+            crossbeam::channel::bounded(editor::PARAM_UPDATE_QUEUE_CAPACITY);
+
+        Some(Box::new(editor::BevyEditor {
+            bevy_state: self.bevy_state,
+            window_config: self.window_config,
+            extensions: self.extensions,
+            build: Arc::new(build),
+
+            param_updates_sender,
+            param_updates_receiver,
+            param_values_changed: Arc::new(AtomicBool::new(false)),
+
+            scaling_factor: AtomicCell::new(self.scaling_factor),
+            pending_scaling_factor: Arc::new(AtomicCell::new(None)),
+        }))
+    }
+}
+
+/// The editor's persistent state. This is serialized with a version number, and payloads written by
+/// older versions of this crate are migrated when they are restored.
+#[derive(Debug)]
 pub struct BevyState {
-    #[serde(with = "nih_plug::params::persist::serialize_atomic_cell")]
     size: AtomicCell<(u32, u32)>,
-    #[serde(skip)]
+    /// The smallest size in logical pixels the editor can be resized to, if any.
+    min_size: AtomicCell<Option<(u32, u32)>>,
+    /// The largest size in logical pixels the editor can be resized to, if any.
+    max_size: AtomicCell<Option<(u32, u32)>>,
+    /// A fixed `width / height` ratio the editor's size must keep, if any.
+    aspect_ratio: AtomicCell<Option<f32>>,
+    /// The user's zoom factor, applied on top of the host's DPI scaling.
+    zoom: AtomicCell<f32>,
+    /// The plugin's own UI state, see [`ui_state_plugin::NIHUiStatePlugin`].
+    ui_state: RwLock<Option<serde_json::Value>>,
+    /// Incremented whenever `ui_state` is replaced by restoring the plugin's state, so an open
+    /// editor knows to reload it.
+    ui_state_version: AtomicU32,
     open: AtomicBool,
 }
 
 impl<'a> PersistentField<'a, BevyState> for Arc<BevyState> {
     fn set(&self, new_value: BevyState) {
-        self.size.store(new_value.size.load());
+        // The constraints are persisted alongside the size. Payloads from before they were stored
+        // don't have them, in which case the plugin's own constraints are kept.
+        if let Some(min_size) = new_value.min_size.load() {
+            self.min_size.store(Some(min_size));
+        }
+        if let Some(max_size) = new_value.max_size.load() {
+            self.max_size.store(Some(max_size));
+        }
+        if let Some(aspect_ratio) = new_value.aspect_ratio.load() {
+            self.aspect_ratio.store(Some(aspect_ratio));
+        }
+        self.size.store(self.clamp_size(new_value.size.load()));
+        self.set_zoom(new_value.zoom.load());
+
+        *self.ui_state.write().unwrap() = new_value.ui_state.into_inner().unwrap();
+        self.ui_state_version.fetch_add(1, Ordering::AcqRel);
     }
 
     fn map<F, R>(&self, f: F) -> R
@@ -53,23 +290,200 @@ impl<'a> PersistentField<'a, BevyState> for Arc<BevyState> {
 }
 
 impl BevyState {
+    pub const MIN_ZOOM: f32 = 0.25;
+    pub const MAX_ZOOM: f32 = 4.0;
+
     /// Initialize the GUI's state. This value can be passed to [`create_iced_editor()`]. The window
     /// size is in logical pixels, so before it is multiplied by the DPI scaling factor.
     pub fn from_size(width: u32, height: u32) -> Arc<BevyState> {
         Arc::new(BevyState {
             size: AtomicCell::new((width, height)),
+            min_size: AtomicCell::new(None),
+            max_size: AtomicCell::new(None),
+            aspect_ratio: AtomicCell::new(None),
+            zoom: BevyState::default_zoom(),
+            ui_state: RwLock::new(None),
+            ui_state_version: AtomicU32::new(0),
             open: AtomicBool::new(false),
         })
     }
 
+    fn default_zoom() -> AtomicCell<f32> {
+        AtomicCell::new(persist::DEFAULT_ZOOM)
+    }
+
+    /// Prevent the editor from being resized below `width` by `height` logical pixels.
+    pub fn with_min_size(self: Arc<Self>, width: u32, height: u32) -> Arc<BevyState> {
+        self.min_size.store(Some((width.max(1), height.max(1))));
+        self.size.store(self.clamp_size(self.size()));
+        self
+    }
+
+    /// Prevent the editor from being resized beyond `width` by `height` logical pixels.
+    pub fn with_max_size(self: Arc<Self>, width: u32, height: u32) -> Arc<BevyState> {
+        self.max_size.store(Some((width.max(1), height.max(1))));
+        self.size.store(self.clamp_size(self.size()));
+        self
+    }
+
+    /// Lock the editor's `width / height` ratio, so a layout designed at 4:3 is never stretched.
+    pub fn with_aspect_ratio(self: Arc<Self>, aspect_ratio: f32) -> Arc<BevyState> {
+        self.aspect_ratio
+            .store((aspect_ratio.is_finite() && aspect_ratio > 0.0).then_some(aspect_ratio));
+        self.size.store(self.clamp_size(self.size()));
+        self
+    }
+
+    /// The smallest size the editor can be resized to, if set.
+    pub fn min_size(&self) -> Option<(u32, u32)> {
+        self.min_size.load()
+    }
+
+    /// The largest size the editor can be resized to, if set.
+    pub fn max_size(&self) -> Option<(u32, u32)> {
+        self.max_size.load()
+    }
+
+    /// The `width / height` ratio the editor is locked to, if set.
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        self.aspect_ratio.load()
+    }
+
+    /// Constrain a `(width, height)` pair in logical pixels to the minimum and maximum sizes and to
+    /// the aspect ratio. With a fixed aspect ratio the width and the height both contribute to the
+    /// result, so dragging either edge of the window resizes it.
+    pub fn clamp_size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let (min_width, min_height) = self.min_size().unwrap_or((1, 1));
+        let (max_width, max_height) = self.max_size().unwrap_or((u32::MAX, u32::MAX));
+        let (min_width, min_height) = (min_width as f32, min_height as f32);
+        let (max_width, max_height) = (
+            (max_width as f32).max(min_width),
+            (max_height as f32).max(min_height),
+        );
+
+        match self.aspect_ratio() {
+            Some(ratio) => {
+                let width = (width as f32 + height as f32 * ratio) / 2.0;
+                let min_width = min_width.max(min_height * ratio);
+                let max_width = max_width.min(max_height * ratio).max(min_width);
+                let width = width.clamp(min_width, max_width);
+
+                (width.round() as u32, (width / ratio).round().max(1.0) as u32)
+            }
+            None => (
+                (width as f32).clamp(min_width, max_width) as u32,
+                (height as f32).clamp(min_height, max_height) as u32,
+            ),
+        }
+    }
+
     /// Returns a `(width, height)` pair for the current size of the GUI in logical pixels.
     pub fn size(&self) -> (u32, u32) {
         self.size.load()
     }
 
+    /// Returns a `(width, height)` pair for the size of the window in logical pixels, which is the
+    /// GUI's size multiplied by the user's zoom factor.
+    pub fn scaled_size(&self) -> (u32, u32) {
+        let (width, height) = self.size();
+        let zoom = self.zoom();
+
+        (
+            (width as f32 * zoom).round() as u32,
+            (height as f32 * zoom).round() as u32,
+        )
+    }
+
+    /// The user's zoom factor, e.g. `1.5` for a 150% GUI size.
+    pub fn zoom(&self) -> f32 {
+        self.zoom.load()
+    }
+
+    /// Change the user's zoom factor. This is clamped between [`MIN_ZOOM`][Self::MIN_ZOOM] and
+    /// [`MAX_ZOOM`][Self::MAX_ZOOM]. Use a [`ZoomRequest`][window_plugin::ZoomRequest] to change
+    /// the zoom factor while the editor is open.
+    pub fn set_zoom(&self, zoom: f32) {
+        let zoom = if zoom.is_finite() { zoom } else { 1.0 };
+        self.zoom.store(zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM));
+    }
+
+    /// The plugin's own UI state, if any has been stored and it can be deserialized as a `T`.
+    pub fn ui_state<T: DeserializeOwned>(&self) -> Option<T> {
+        let ui_state = self.ui_state.read().unwrap();
+        let value = ui_state.as_ref()?;
+
+        match serde_json::from_value(value.clone()) {
+            Ok(ui_state) => Some(ui_state),
+            Err(err) => {
+                nih_plug::nih_warn!("Could not restore the editor's UI state: {err}");
+                None
+            }
+        }
+    }
+
+    /// Store the plugin's own UI state so it is persisted with the plugin's state.
+    pub fn set_ui_state<T: Serialize>(&self, ui_state: &T) {
+        match serde_json::to_value(ui_state) {
+            Ok(value) => *self.ui_state.write().unwrap() = Some(value),
+            Err(err) => nih_plug::nih_warn!("Could not store the editor's UI state: {err}"),
+        }
+    }
+
+    pub(crate) fn ui_state_version(&self) -> u32 {
+        self.ui_state_version.load(Ordering::Acquire)
+    }
+
     /// Whether the GUI is currently visible.
     // Called `is_open()` instead of `open()` to avoid the ambiguity.
     pub fn is_open(&self) -> bool {
         self.open.load(Ordering::Acquire)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_size_without_constraints() {
+        let state = BevyState::from_size(800, 400);
+        assert_eq!(state.clamp_size((300, 200)), (300, 200));
+        assert_eq!(state.clamp_size((0, 0)), (1, 1));
+    }
+
+    #[test]
+    fn clamp_size_to_min_and_max() {
+        let state = BevyState::from_size(800, 500)
+            .with_min_size(400, 300)
+            .with_max_size(1600, 1200);
+        assert_eq!(state.clamp_size((100, 100)), (400, 300));
+        assert_eq!(state.clamp_size((2000, 2000)), (1600, 1200));
+        assert_eq!(state.clamp_size((800, 500)), (800, 500));
+    }
+
+    #[test]
+    fn clamp_size_to_aspect_ratio() {
+        let state = BevyState::from_size(800, 400).with_aspect_ratio(2.0);
+        // Both edges contribute, so dragging only one of them still resizes the window
+        assert_eq!(state.clamp_size((900, 400)), (850, 425));
+        assert_eq!(state.clamp_size((800, 500)), (900, 450));
+    }
+
+    #[test]
+    fn clamp_size_to_aspect_ratio_within_bounds() {
+        let state = BevyState::from_size(800, 400)
+            .with_aspect_ratio(2.0)
+            .with_min_size(400, 300)
+            .with_max_size(1000, 400);
+        // The minimum height and the maximum height win over the widths here
+        assert_eq!(state.clamp_size((100, 100)), (600, 300));
+        assert_eq!(state.clamp_size((2000, 1000)), (800, 400));
+    }
+
+    #[test]
+    fn invalid_aspect_ratios_are_ignored() {
+        let state = BevyState::from_size(800, 400).with_aspect_ratio(f32::NAN);
+        assert_eq!(state.aspect_ratio(), None);
+        assert_eq!(state.clamp_size((300, 200)), (300, 200));
+    }
+}
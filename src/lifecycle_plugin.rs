@@ -0,0 +1,113 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+
+use bevy::{
+    app::AppExit,
+    ecs::schedule::ScheduleLabel,
+    prelude::*,
+    window::{WindowCloseRequested, WindowClosing},
+};
+
+//<========== Resources ==========>//
+
+/// Tracks whether the editor is being closed.
+#[derive(Resource)]
+pub(crate) struct NIHEditorClosing {
+    /// Set by the editor handle when the host closes the editor.
+    requested: Arc<AtomicBool>,
+    /// Whether [`EditorClosing`] has already been sent.
+    handled: bool,
+}
+
+impl NIHEditorClosing {
+    pub(crate) fn new(requested: Arc<AtomicBool>) -> Self {
+        Self {
+            requested,
+            handled: false,
+        }
+    }
+}
+
+//<========== Plugin ==========>//
+
+/// Notifies the app when the editor opens and closes, see [`EditorOpened`] and [`EditorClosing`].
+pub struct NIHLifecyclePlugin;
+
+impl Plugin for NIHLifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_schedule(EditorClose)
+            .add_event::<EditorOpened>()
+            .add_event::<EditorClosing>()
+            .add_systems(Startup, editor_opened_system)
+            .add_systems(First, editor_closing_system)
+            // The host can close the editor while a frame is running, checking again at the end of
+            // the frame gives the close step one more chance to run before the window is gone
+            .add_systems(Last, editor_closing_system);
+    }
+}
+
+//<========== Schedules ==========>//
+
+/// Runs once right after [`EditorClosing`] has been sent, before the editor's window disappears.
+/// Use this to flush unsaved UI state, stop telemetry consumers, or end gestures.
+///
+/// When the host closes the editor, the editor handle flags the app as closing and then closes the
+/// window. This schedule runs on the app's own thread the next time the app updates, so it only
+/// runs if bevy_baseview still updates the app after the window was asked to close. It always runs
+/// when the close was requested through Bevy's own window or exit events. Open parameter gestures
+/// and the UI state from [`NIHUiStatePlugin`][crate::ui_state_plugin::NIHUiStatePlugin] don't
+/// depend on this, they're ended and stored by the editor handle and on every change respectively.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EditorClose;
+
+//<========== Events ==========>//
+
+/// Sent on the first frame after the editor has been opened.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct EditorOpened;
+
+/// Sent once when the editor is about to close. See [`EditorClose`] for when this is sent.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct EditorClosing;
+
+//<========== Systems ==========>//
+
+fn editor_opened_system(mut opened_events: EventWriter<EditorOpened>) {
+    opened_events.send(EditorOpened);
+}
+
+// TODO: The close step isn't guaranteed to run when the host closes the editor, since the window is
+//       closed right after the closing flag is set. This needs a close callback in bevy_baseview
+//       that updates the app one last time before the window is destroyed.
+fn editor_closing_system(world: &mut World) {
+    let closing = world.resource::<NIHEditorClosing>();
+    if closing.handled {
+        return;
+    }
+
+    let requested = closing.requested.load(Ordering::Acquire)
+        || has_events::<WindowCloseRequested>(world)
+        || has_events::<WindowClosing>(world)
+        || has_events::<AppExit>(world);
+    if requested {
+        run_close(world);
+    }
+}
+
+/// Send [`EditorClosing`] and run the [`EditorClose`] schedule, unless that already happened.
+fn run_close(world: &mut World) {
+    let mut closing = world.resource_mut::<NIHEditorClosing>();
+    if closing.handled {
+        return;
+    }
+    closing.handled = true;
+
+    world.send_event(EditorClosing);
+    let _ = world.try_run_schedule(EditorClose);
+}
+
+fn has_events<E: Event>(world: &World) -> bool {
+    world
+        .get_resource::<Events<E>>()
+        .is_some_and(|events| !events.is_empty())
+}
@@ -1,7 +1,18 @@
-use std::sync::Arc;
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex};
 
-use bevy::{prelude::*, utils::HashMap};
-use nih_plug::{params::Params, prelude::ParamPtr};
+use bevy::{
+    ecs::{
+        component::{ComponentHooks, StorageType},
+        world::DeferredWorld,
+    },
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use crossbeam::channel::Receiver;
+use nih_plug::{params::Params, prelude::{GuiContext, ParamPtr}};
+
+/// Moved to [`window_plugin`][crate::window_plugin], re-exported here for compatibility.
+pub use crate::window_plugin::ResizeRequest;
 
 //<========== Resources ==========>//
 
@@ -12,10 +23,159 @@ impl NIHContext {
     pub fn new(ctx: Arc<dyn nih_plug::prelude::GuiContext>) -> Self {
         Self(ctx)
     }
+
+    pub(crate) fn context(&self) -> &dyn GuiContext {
+        &*self.0
+    }
 }
 
+/// The parameters that are currently in a gesture, along with the sources taking part in each of
+/// those gestures. Several parameters can be in a gesture at the same time, and the host only ever
+/// sees a single gesture per parameter no matter how many entities are bound to it.
 #[derive(Resource, Default)]
-pub struct NIHCurrentParam(Option<Entity>);
+pub struct NIHGestures {
+    sources: HashMap<ParamPtr, Vec<GestureSource>>,
+    open: OpenGestures,
+}
+
+/// Who is taking part in a parameter gesture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GestureSource {
+    /// A gesture started through [`ParamEvent::begin()`].
+    Entity(Entity),
+    /// A gesture started by parameter ID or by [`ParamPtr`]. All of these share a single source per
+    /// parameter.
+    Direct,
+}
+
+impl NIHGestures {
+    pub(crate) fn new(open: OpenGestures) -> Self {
+        Self {
+            sources: HashMap::new(),
+            open,
+        }
+    }
+
+    /// Whether `param` is currently in a gesture.
+    pub fn is_active(&self, param: ParamPtr) -> bool {
+        self.sources.contains_key(&param)
+    }
+
+    /// The sources taking part in `param`'s gesture, if any.
+    pub fn sources(&self, param: ParamPtr) -> &[GestureSource] {
+        self.sources.get(&param).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn contains(&self, param: ParamPtr, source: GestureSource) -> bool {
+        self.sources(param).contains(&source)
+    }
+}
+
+/// The parameters the host currently considers to be in a gesture. This is shared with the editor
+/// handle so those gestures can still be ended when the editor closes.
+#[derive(Clone, Default)]
+pub(crate) struct OpenGestures(Arc<Mutex<HashSet<ParamPtr>>>);
+
+impl OpenGestures {
+    fn begin(&self, ctx: &dyn GuiContext, param: ParamPtr) {
+        if self.0.lock().unwrap().insert(param) {
+            unsafe { ctx.raw_begin_set_parameter(param) };
+        }
+    }
+
+    fn end(&self, ctx: &dyn GuiContext, param: ParamPtr) {
+        if self.0.lock().unwrap().remove(&param) {
+            unsafe { ctx.raw_end_set_parameter(param) };
+        }
+    }
+
+    /// End every gesture that is still open.
+    pub(crate) fn end_all(&self, ctx: &dyn GuiContext) {
+        for param in self.0.lock().unwrap().drain() {
+            unsafe { ctx.raw_end_set_parameter(param) };
+        }
+    }
+}
+
+/// The entities bound to each parameter, so host notifications only touch the entities they're
+/// about instead of every [`NIHParam`]. This is kept up to date by [`NIHParam`]'s component hooks.
+#[derive(Resource, Default)]
+struct NIHParamEntities {
+    by_param: HashMap<ParamPtr, Vec<Entity>>,
+    by_entity: HashMap<Entity, ParamPtr>,
+}
+
+impl NIHParamEntities {
+    fn insert(&mut self, entity: Entity, param: ParamPtr) {
+        self.remove(entity);
+        self.by_param.entry(param).or_default().push(entity);
+        self.by_entity.insert(entity, param);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        let Some(param) = self.by_entity.remove(&entity) else {
+            return;
+        };
+
+        if let Some(entities) = self.by_param.get_mut(&param) {
+            entities.retain(|&other| other != entity);
+            if entities.is_empty() {
+                self.by_param.remove(&param);
+            }
+        }
+    }
+
+    fn entities(&self, param: ParamPtr) -> &[Entity] {
+        self.by_param.get(&param).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// How strictly [`NIHParamPlugin`] treats invalid gestures. The plugin starts out with the lenient
+/// defaults, a build function can insert its own copy, e.g. with
+/// `strict_gestures: cfg!(debug_assertions)`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct NIHParamConfig {
+    /// Panic on invalid parameter gestures instead of recovering from them. Useful for catching
+    /// widget bugs in development builds, e.g. by setting this to `cfg!(debug_assertions)`.
+    pub strict_gestures: bool,
+}
+
+/// Parameter notifications received through the editor's callbacks. These may arrive on the audio
+/// thread where nothing may be allocated, so they only carry a hash of the parameter's ID, see
+/// [`param_id_hash()`]. They are queued up and resolved by the Bevy app at the start of every frame.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum HostParamUpdate {
+    Value {
+        id_hash: u32,
+        normalized_value: f32,
+    },
+    Modulation {
+        id_hash: u32,
+        modulation_offset: f32,
+    },
+}
+
+#[derive(Resource)]
+pub(crate) struct NIHHostUpdates {
+    receiver: Receiver<HostParamUpdate>,
+    /// Set when the host has changed all parameter values at once, for instance when loading a
+    /// preset, or when individual updates had to be dropped.
+    values_changed: Arc<AtomicBool>,
+}
+
+impl NIHHostUpdates {
+    pub(crate) fn new(receiver: Receiver<HostParamUpdate>, values_changed: Arc<AtomicBool>) -> Self {
+        Self { receiver, values_changed }
+    }
+}
+
+/// A 32-bit FNV-1a hash of a parameter ID. This lets the editor's callbacks identify parameters
+/// without allocating, [`NIHParams`] maps the hashes back to the IDs.
+pub(crate) fn param_id_hash(id: &str) -> u32 {
+    id.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
 
 
 //<========== Plugin ==========>//
@@ -25,35 +185,288 @@ pub struct NIHParamPlugin;
 impl Plugin for NIHParamPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<NIHParamConfig>()
+            .init_resource::<NIHParamEntities>()
             .add_event::<ParamEvent>()
-            .add_event::<ResizeRequest>()
-            .add_systems(PostUpdate, param_system);
+            .add_event::<ParamGestureError>()
+            .add_event::<ParamValueChanged>()
+            .add_event::<ParamModulationChanged>()
+            .add_event::<ParamsReloaded>()
+            .add_systems(
+                PreUpdate,
+                (
+                    host_update_system,
+                    (param_value_system, param_modulation_system),
+                    param_reload_system,
+                )
+                    .chain(),
+            )
+            .add_systems(PostUpdate, (param_system, dangling_gesture_system).chain());
     }
 }
 
 //<========== Compnents ==========>//
 
-#[derive(Component)]
+/// Binds an entity to a plugin parameter, see [`ParamEvent`] for changing the parameter. The value
+/// is kept in sync with the host, but only for parameters that are present in the [`NIHParams`]
+/// resource since that's how the host's notifications are mapped back to parameters. Insert
+/// [`NIHParams::from_params()`] from the editor's build function so this covers every parameter.
 pub struct NIHParam {
-    param: ParamPtr
+    param: ParamPtr,
+    normalized_value: f32,
+    modulated_normalized_value: f32,
+}
+
+// The hooks also run when a `NIHParam` replaces an existing one, so the index follows entities that
+// get rebound to another parameter
+impl Component for NIHParam {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+
+    fn register_component_hooks(hooks: &mut ComponentHooks) {
+        hooks
+            .on_insert(|mut world: DeferredWorld, entity, _| {
+                let param = world.get::<NIHParam>(entity).unwrap().param;
+                if let Some(mut index) = world.get_resource_mut::<NIHParamEntities>() {
+                    index.insert(entity, param);
+                }
+            })
+            .on_remove(|mut world: DeferredWorld, entity, _| {
+                if let Some(mut index) = world.get_resource_mut::<NIHParamEntities>() {
+                    index.remove(entity);
+                }
+            });
+    }
 }
 
 impl NIHParam {
     pub fn new(param: ParamPtr) -> Self {
-        Self { param }
+        Self {
+            param,
+            normalized_value: unsafe { param.unmodulated_normalized_value() },
+            modulated_normalized_value: unsafe { param.modulated_normalized_value() },
+        }
+    }
+
+    pub fn param(&self) -> ParamPtr {
+        self.param
+    }
+
+    /// The parameter's last known normalized value. This is kept in sync with the host, so widgets
+    /// can react to automation by looking for `Changed<NIHParam>`.
+    pub fn normalized_value(&self) -> f32 {
+        self.normalized_value
+    }
+
+    /// The parameter's last known normalized value with the host's monophonic modulation applied.
+    /// This is the same as [`normalized_value()`][Self::normalized_value()] when the parameter isn't
+    /// being modulated.
+    pub fn modulated_normalized_value(&self) -> f32 {
+        self.modulated_normalized_value
+    }
+
+    /// The parameter's display name.
+    pub fn name(&self) -> &str {
+        unsafe { self.param.name() }
+    }
+
+    /// The parameter's unit, if it has one, e.g. `" dB"`.
+    pub fn unit(&self) -> &'static str {
+        unsafe { self.param.unit() }
+    }
+
+    /// Format a normalized value the same way the host would, using the parameter's own
+    /// value-to-string function and optionally its unit.
+    pub fn normalized_value_to_string(&self, normalized: f32, include_unit: bool) -> String {
+        unsafe { self.param.normalized_value_to_string(normalized, include_unit) }
+    }
+
+    /// The parameter's last known value formatted with its unit, e.g. `"-3.00 dB"`.
+    pub fn value_to_string(&self) -> String {
+        self.normalized_value_to_string(self.normalized_value, true)
+    }
+
+    /// Parse user input into a normalized value using the parameter's own string-to-value
+    /// function. Returns `None` if the text could not be parsed.
+    pub fn string_to_normalized_value(&self, string: &str) -> Option<f32> {
+        unsafe { self.param.string_to_normalized_value(string) }
+    }
+}
+
+/// The CLAP monophonic modulation offset for the parameter in the entity's [`NIHParam`]. This gets
+/// added to the entity the first time the host modulates that parameter.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct NIHParamModulation {
+    offset: f32,
+}
+
+impl NIHParamModulation {
+    /// The modulation offset in normalized units.
+    pub fn offset(&self) -> f32 {
+        self.offset
     }
 }
 
 //<========== Resources ==========>//
 
+/// A parameter known to [`NIHParams`], along with its ID and the group it was declared in.
+#[derive(Debug, Clone)]
+pub struct NIHParamEntry {
+    pub id: String,
+    pub param: ParamPtr,
+    /// The `/`-separated path of the nested parameter groups containing this parameter. This is
+    /// empty for top-level parameters.
+    pub group: String,
+}
+
 #[derive(Resource)]
 pub struct NIHParams {
-    pub params: HashMap<String, ParamPtr>
+    /// The parameters in declaration order.
+    entries: Vec<NIHParamEntry>,
+    /// Indices into `entries`, keyed by parameter ID.
+    by_id: HashMap<String, usize>,
+    /// Indices into `entries`, keyed by [`param_id_hash()`].
+    by_hash: HashMap<u32, usize>,
+    /// Hashes shared by more than one parameter ID. These can't be resolved to a single parameter.
+    colliding_hashes: HashSet<u32>,
 }
 
 impl NIHParams {
+    /// Build the parameter list from a hand-made map. The parameters don't belong to any group, and
+    /// since the map is unordered they are sorted by ID instead of by declaration order.
     pub fn new(params: HashMap<String, ParamPtr>) -> Self {
-        Self { params }
+        let mut entries: Vec<NIHParamEntry> = params
+            .into_iter()
+            .map(|(id, param)| NIHParamEntry {
+                id,
+                param,
+                group: String::new(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Self::from_entries(entries)
+    }
+
+    /// Collect every parameter from the plugin's [`Params`] object, including the ones in nested
+    /// parameter groups.
+    pub fn from_params(params: Arc<dyn Params>) -> Self {
+        let entries = params
+            .param_map()
+            .into_iter()
+            .map(|(id, param, group)| NIHParamEntry { id, param, group })
+            .collect();
+
+        Self::from_entries(entries)
+    }
+
+    fn from_entries(entries: Vec<NIHParamEntry>) -> Self {
+        let by_id = entries
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| (entry.id.clone(), idx))
+            .collect();
+        let mut by_hash: HashMap<u32, usize> = HashMap::new();
+        let mut colliding_hashes = HashSet::new();
+        for (idx, entry) in entries.iter().enumerate() {
+            let id_hash = param_id_hash(&entry.id);
+            if let Some(other) = by_hash.insert(id_hash, idx) {
+                warn!(
+                    "The parameter IDs '{}' and '{}' have the same hash, host changes to either of \
+                     them make the editor reload every parameter",
+                    entries[other].id, entry.id
+                );
+                colliding_hashes.insert(id_hash);
+            }
+        }
+        by_hash.retain(|id_hash, _| !colliding_hashes.contains(id_hash));
+
+        Self {
+            entries,
+            by_id,
+            by_hash,
+            colliding_hashes,
+        }
+    }
+
+    /// Look up a parameter by its ID.
+    pub fn get(&self, id: &str) -> Option<ParamPtr> {
+        self.entry(id).map(|entry| entry.param)
+    }
+
+    /// Look up a parameter and its group by the parameter's ID.
+    pub fn entry(&self, id: &str) -> Option<&NIHParamEntry> {
+        self.by_id.get(id).map(|&idx| &self.entries[idx])
+    }
+
+    /// All parameters keyed by their IDs. This is what the former public `params` field held, prefer
+    /// [`get()`][Self::get()] or [`iter()`][Self::iter()] in new code.
+    pub fn map(&self) -> HashMap<&str, ParamPtr> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.param))
+            .collect()
+    }
+
+    fn entry_by_hash(&self, id_hash: u32) -> Option<&NIHParamEntry> {
+        self.by_hash.get(&id_hash).map(|&idx| &self.entries[idx])
+    }
+
+    fn is_colliding_hash(&self, id_hash: u32) -> bool {
+        self.colliding_hashes.contains(&id_hash)
+    }
+
+    /// Find the ID of a parameter.
+    pub fn id_of(&self, param: ParamPtr) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|entry| entry.param == param)
+            .map(|entry| entry.id.as_str())
+    }
+
+    /// All parameters in declaration order.
+    pub fn iter(&self) -> impl Iterator<Item = &NIHParamEntry> {
+        self.entries.iter()
+    }
+
+    /// The parameters declared directly in `group`, in declaration order. Use an empty string for
+    /// the top-level parameters.
+    pub fn group<'a>(&'a self, group: &'a str) -> impl Iterator<Item = &'a NIHParamEntry> + 'a {
+        self.entries.iter().filter(move |entry| entry.group == group)
+    }
+
+    /// The parameters in `group` and all of its nested groups, in declaration order.
+    pub fn group_recursive<'a>(
+        &'a self,
+        group: &'a str,
+    ) -> impl Iterator<Item = &'a NIHParamEntry> + 'a {
+        self.entries.iter().filter(move |entry| {
+            group.is_empty()
+                || entry.group == group
+                || entry
+                    .group
+                    .strip_prefix(group)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    /// The distinct group paths in the order they were first declared.
+    pub fn groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = Vec::new();
+        for entry in &self.entries {
+            if !groups.contains(&entry.group.as_str()) {
+                groups.push(&entry.group);
+            }
+        }
+
+        groups
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 }
 
@@ -65,95 +478,745 @@ pub enum ParamAction {
     End,
 }
 
+/// The parameter a [`ParamEvent`] applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamTarget {
+    /// The parameter in the entity's [`NIHParam`] component.
+    Entity(Entity),
+    /// A parameter by its ID. This is resolved through the [`NIHParams`] resource.
+    Id(String),
+    /// A parameter directly, without going through an entity.
+    Param(ParamPtr),
+}
+
+impl ParamTarget {
+    fn source(&self) -> GestureSource {
+        match self {
+            ParamTarget::Entity(entity) => GestureSource::Entity(*entity),
+            ParamTarget::Id(_) | ParamTarget::Param(_) => GestureSource::Direct,
+        }
+    }
+}
+
+impl std::fmt::Display for ParamTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamTarget::Entity(entity) => write!(f, "entity {entity}"),
+            ParamTarget::Id(id) => write!(f, "parameter '{id}'"),
+            ParamTarget::Param(param) => write!(f, "parameter '{}'", unsafe { param.name() }),
+        }
+    }
+}
+
 #[derive(Event)]
 pub struct ParamEvent {
-    id: Entity, 
+    target: ParamTarget,
     action: ParamAction,
 }
 
 impl ParamEvent {
     pub fn begin(id: Entity) -> Self {
         Self{
-            id,
+            target: ParamTarget::Entity(id),
             action: ParamAction::Begin
         }
     }
 
     pub fn set(id: Entity, norm_val: f32) -> Self {
         Self{
-            id,
+            target: ParamTarget::Entity(id),
             action: ParamAction::Set(norm_val)
         }
     }
 
     pub fn end(id: Entity) -> Self {
         Self{
-            id,
+            target: ParamTarget::Entity(id),
             action: ParamAction::End
         }
     }
+
+    /// Begin a gesture for the parameter with the ID `id`, see [`ParamTarget::Id`].
+    pub fn begin_by_id(id: impl Into<String>) -> Self {
+        Self {
+            target: ParamTarget::Id(id.into()),
+            action: ParamAction::Begin,
+        }
+    }
+
+    /// Set the parameter with the ID `id`, see [`ParamTarget::Id`]. Outside of a gesture this is a
+    /// one-off change, like a keyboard shortcut or preset logic would make, and it's wrapped in its
+    /// own gesture.
+    pub fn set_by_id(id: impl Into<String>, norm_val: f32) -> Self {
+        Self {
+            target: ParamTarget::Id(id.into()),
+            action: ParamAction::Set(norm_val),
+        }
+    }
+
+    /// End the gesture for the parameter with the ID `id`, see [`ParamTarget::Id`].
+    pub fn end_by_id(id: impl Into<String>) -> Self {
+        Self {
+            target: ParamTarget::Id(id.into()),
+            action: ParamAction::End,
+        }
+    }
+
+    /// Begin a gesture for `param` directly.
+    pub fn begin_param(param: ParamPtr) -> Self {
+        Self {
+            target: ParamTarget::Param(param),
+            action: ParamAction::Begin,
+        }
+    }
+
+    /// Set `param` directly. Like [`set_by_id()`][Self::set_by_id()] this doesn't need a gesture.
+    pub fn set_param(param: ParamPtr, norm_val: f32) -> Self {
+        Self {
+            target: ParamTarget::Param(param),
+            action: ParamAction::Set(norm_val),
+        }
+    }
+
+    /// End the gesture for `param` directly.
+    pub fn end_param(param: ParamPtr) -> Self {
+        Self {
+            target: ParamTarget::Param(param),
+            action: ParamAction::End,
+        }
+    }
+
+    pub fn target(&self) -> &ParamTarget {
+        &self.target
+    }
+
+    pub fn action(&self) -> &ParamAction {
+        &self.action
+    }
+}
+
+/// Sent instead of panicking when [`ParamEvent`]s don't form a valid gesture. The gesture is
+/// recovered as described for each variant and a warning is logged. See
+/// [`NIHParamConfig::strict_gestures`] to panic instead.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub enum ParamGestureError {
+    /// A gesture was begun for a parameter the target is already in a gesture for. The duplicate
+    /// `Begin` is ignored.
+    AlreadyStarted { target: ParamTarget },
+    /// An entity set its parameter, or any target ended a gesture, without starting a gesture first.
+    /// A stray `Set` joins the parameter's gesture if another source is holding one and is otherwise
+    /// sent as a complete gesture of its own. A stray `End` is ignored. Setting a parameter by ID or
+    /// by [`ParamPtr`] outside of a gesture is a valid one-off change and doesn't cause this.
+    NotStarted { target: ParamTarget },
+    /// The entity doesn't have a [`NIHParam`] component. The event is ignored.
+    MissingParam { entity: Entity },
+    /// There is no parameter with this ID in [`NIHParams`], or that resource doesn't exist. The event
+    /// is ignored.
+    UnknownId { id: String },
+}
+
+impl std::fmt::Display for ParamGestureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamGestureError::AlreadyStarted { target } => write!(
+                f,
+                "A gesture has already begun for {target}; cannot begin another one for the same \
+                 parameter"
+            ),
+            ParamGestureError::NotStarted { target } => write!(
+                f,
+                "Cannot set a parameter or end a gesture for {target} without beginning a gesture first"
+            ),
+            ParamGestureError::MissingParam { entity } => write!(
+                f,
+                "Error while retrieving the NIH Param component for {entity}; make sure it is present \
+                 in the entity"
+            ),
+            ParamGestureError::UnknownId { id } => write!(
+                f,
+                "Unknown parameter ID '{id}'; make sure it is present in the NIHParams resource"
+            ),
+        }
+    }
 }
 
-#[derive(Event)]
-pub struct ResizeRequest;
+/// Sent whenever the host changes a parameter's value, for instance through automation.
+#[derive(Event, Debug, Clone)]
+pub struct ParamValueChanged {
+    pub id: String,
+    pub normalized_value: f32,
+}
+
+/// Sent whenever the host changes a parameter's monophonic modulation offset.
+#[derive(Event, Debug, Clone)]
+pub struct ParamModulationChanged {
+    pub id: String,
+    pub modulation_offset: f32,
+}
+
+/// Sent after every [`NIHParam`] has been re-read from its parameter because the host changed all
+/// values at once, for instance when loading a preset or restoring the plugin's state.
+#[derive(Event, Debug, Clone, Default)]
+pub struct ParamsReloaded;
 
 //<========== Systems ==========>//
 
+fn host_update_system(
+    updates: Res<NIHHostUpdates>,
+    params: Option<Res<NIHParams>>,
+    mut warned_missing: Local<bool>,
+    mut warned_hashes: Local<HashSet<u32>>,
+    mut value_events: EventWriter<ParamValueChanged>,
+    mut modulation_events: EventWriter<ParamModulationChanged>,
+) {
+    // The host's notifications can only be mapped back to parameter IDs through `NIHParams`, so
+    // without it or with parameters missing from it widgets would silently never update. Each of
+    // these is only logged once to not flood the log during automation.
+    let mut resolve = |id_hash: u32| {
+        let Some(params) = params.as_ref() else {
+            if !std::mem::replace(&mut *warned_missing, true) {
+                warn!(
+                    "The host changed a parameter but there's no NIHParams resource, insert one \
+                     with NIHParams::from_params() to keep NIHParam components in sync"
+                );
+            }
+            return None;
+        };
+
+        // Resolving a hash that's shared by several parameters to either of them would update the
+        // wrong widgets, so this falls back to re-reading every parameter instead
+        if params.is_colliding_hash(id_hash) {
+            updates.values_changed.store(true, Ordering::Release);
+            return None;
+        }
+
+        let entry = params.entry_by_hash(id_hash);
+        if entry.is_none() && warned_hashes.insert(id_hash) {
+            warn!(
+                "The host changed a parameter that's missing from the NIHParams resource (ID hash \
+                 {id_hash:#010x}), NIHParam components bound to it won't be kept in sync"
+            );
+        }
+        entry.map(|entry| entry.id.clone())
+    };
+
+    for update in updates.receiver.try_iter() {
+        match update {
+            HostParamUpdate::Value { id_hash, normalized_value } => {
+                let Some(id) = resolve(id_hash) else {
+                    continue;
+                };
+
+                value_events.send(ParamValueChanged { id, normalized_value });
+            },
+            HostParamUpdate::Modulation { id_hash, modulation_offset } => {
+                let Some(id) = resolve(id_hash) else {
+                    continue;
+                };
+
+                modulation_events.send(ParamModulationChanged { id, modulation_offset });
+            },
+        }
+    }
+}
+
+/// Updates every [`NIHParam`] bound to a parameter the host has changed. The parameter IDs are
+/// resolved through the [`NIHParams`] resource, so only parameters present there are kept in sync.
+fn param_value_system(
+    params: Option<Res<NIHParams>>,
+    index: Res<NIHParamEntities>,
+    mut value_events: EventReader<ParamValueChanged>,
+    mut q_nih_param: Query<&mut NIHParam>,
+) {
+    let Some(params) = params else {
+        value_events.clear();
+        return;
+    };
+
+    for evt in value_events.read() {
+        let Some(param_ptr) = params.get(&evt.id) else {
+            continue;
+        };
+
+        for &entity in index.entities(param_ptr) {
+            let Ok(mut nih_param) = q_nih_param.get_mut(entity) else {
+                continue;
+            };
+
+            let modulated_normalized_value = unsafe { param_ptr.modulated_normalized_value() };
+            if nih_param.normalized_value != evt.normalized_value
+                || nih_param.modulated_normalized_value != modulated_normalized_value
+            {
+                nih_param.normalized_value = evt.normalized_value;
+                nih_param.modulated_normalized_value = modulated_normalized_value;
+            }
+        }
+    }
+}
+
+/// Re-reads every [`NIHParam`] from its parameter after the host has changed all parameter values.
+/// Entities whose value didn't change are left untouched so change detection stays meaningful.
+fn param_reload_system(
+    updates: Res<NIHHostUpdates>,
+    mut reload_events: EventWriter<ParamsReloaded>,
+    mut q_nih_param: Query<&mut NIHParam>,
+) {
+    if !updates.values_changed.swap(false, Ordering::AcqRel) {
+        return;
+    }
+
+    for mut nih_param in q_nih_param.iter_mut() {
+        let normalized_value = unsafe { nih_param.param.unmodulated_normalized_value() };
+        let modulated_normalized_value = unsafe { nih_param.param.modulated_normalized_value() };
+        if nih_param.normalized_value != normalized_value
+            || nih_param.modulated_normalized_value != modulated_normalized_value
+        {
+            nih_param.normalized_value = normalized_value;
+            nih_param.modulated_normalized_value = modulated_normalized_value;
+        }
+    }
+
+    reload_events.send(ParamsReloaded);
+}
+
+/// Keeps the [`NIHParamModulation`] offsets and the modulated values in [`NIHParam`] in sync with the
+/// host's modulation. Like [`param_value_system`] this relies on the [`NIHParams`] resource.
+fn param_modulation_system(
+    mut commands: Commands,
+    params: Option<Res<NIHParams>>,
+    index: Res<NIHParamEntities>,
+    mut modulation_events: EventReader<ParamModulationChanged>,
+    mut q_nih_param: Query<(&mut NIHParam, Option<&mut NIHParamModulation>)>,
+) {
+    let Some(params) = params else {
+        modulation_events.clear();
+        return;
+    };
+
+    for evt in modulation_events.read() {
+        let Some(param_ptr) = params.get(&evt.id) else {
+            continue;
+        };
+
+        let modulated_normalized_value = unsafe { param_ptr.modulated_normalized_value() };
+        for &entity in index.entities(param_ptr) {
+            let Ok((mut nih_param, modulation)) = q_nih_param.get_mut(entity) else {
+                continue;
+            };
+
+            if nih_param.modulated_normalized_value != modulated_normalized_value {
+                nih_param.modulated_normalized_value = modulated_normalized_value;
+            }
+
+            match modulation {
+                Some(mut modulation) => {
+                    if modulation.offset != evt.modulation_offset {
+                        modulation.offset = evt.modulation_offset;
+                    }
+                },
+                None => {
+                    commands.entity(entity).insert(NIHParamModulation {
+                        offset: evt.modulation_offset,
+                    });
+                },
+            }
+        }
+    }
+}
+
 fn param_system(
     ctx: Res<NIHContext>,
-    mut current: ResMut<NIHCurrentParam>,
+    config: Res<NIHParamConfig>,
+    params: Option<Res<NIHParams>>,
+    mut gestures: ResMut<NIHGestures>,
     mut param_events: EventReader<ParamEvent>,
+    mut error_events: EventWriter<ParamGestureError>,
     q_nih_param: Query<&NIHParam>
-
 ) {
-    for evt  in param_events.read() {
+    let mut report = |error: ParamGestureError| {
+        if config.strict_gestures {
+            panic!("{error}");
+        }
+
+        warn!("{error}");
+        error_events.send(error);
+    };
+
+    for evt in param_events.read() {
+        let param_ptr = match &evt.target {
+            ParamTarget::Entity(entity) => match q_nih_param.get(*entity) {
+                Ok(nih) => nih.param,
+                Err(_) => {
+                    report(ParamGestureError::MissingParam { entity: *entity });
+                    continue;
+                }
+            },
+            ParamTarget::Id(id) => match params.as_ref().and_then(|params| params.get(id)) {
+                Some(param_ptr) => param_ptr,
+                None => {
+                    report(ParamGestureError::UnknownId { id: id.clone() });
+                    continue;
+                }
+            },
+            ParamTarget::Param(param_ptr) => *param_ptr,
+        };
+        let source = evt.target.source();
+
         match evt.action {
             ParamAction::Begin => {
-                if current.0.is_some() {
-                    panic!("A parameter is already being modified;\nCannot begin another gesture")
+                if gestures.contains(param_ptr, source) {
+                    report(ParamGestureError::AlreadyStarted { target: evt.target.clone() });
+                    continue;
                 }
 
-                current.0 = Some(evt.id);
-                let param_ptr = match q_nih_param.get(evt.id) {
-                    Ok(nih) => nih.param,
-                    Err(_) => panic!("Error while retreiving the NIH Param component.
-                        \nMake sure it is present in the entity")
-                };
-                unsafe { ctx.0.raw_begin_set_parameter(param_ptr) };
+                let gestures = &mut *gestures;
+                let sources = gestures.sources.entry(param_ptr).or_default();
+                if sources.is_empty() {
+                    gestures.open.begin(&*ctx.0, param_ptr);
+                }
+                sources.push(source);
             },
             ParamAction::Set(val) => {
-                if current.0.is_none() || current.0.is_some_and(|id| id != evt.id){
-                    panic!("Cannot set a parameter without starting a gesture or during another gesture")
+                // Setting a parameter by ID or directly without beginning a gesture first is a
+                // valid one-off change, entities are expected to always use gestures
+                let one_off = source == GestureSource::Direct;
+                if gestures.contains(param_ptr, source) {
+                    unsafe { ctx.0.raw_set_parameter_normalized(param_ptr, val) };
+                } else if gestures.is_active(param_ptr) {
+                    // Another source is already holding a gesture for this parameter, so this can
+                    // piggyback on that one
+                    if !one_off {
+                        report(ParamGestureError::NotStarted { target: evt.target.clone() });
+                    }
+                    unsafe { ctx.0.raw_set_parameter_normalized(param_ptr, val) };
+                } else {
+                    if !one_off {
+                        report(ParamGestureError::NotStarted { target: evt.target.clone() });
+                    }
+                    unsafe {
+                        ctx.0.raw_begin_set_parameter(param_ptr);
+                        ctx.0.raw_set_parameter_normalized(param_ptr, val);
+                        ctx.0.raw_end_set_parameter(param_ptr);
+                    };
                 }
-
-                let param_ptr = match q_nih_param.get(evt.id) {
-                    Ok(nih) => nih.param,
-                    Err(_) => panic!("Error while retreiving the NIH Param component.
-                        \nMake sure it is present in the entity")
-                };
-
-                unsafe {
-                    ctx.0.raw_set_parameter_normalized(
-                        param_ptr, 
-                        val
-                    )
-                };
             },
             ParamAction::End => {
-                if current.0.is_none() || current.0.is_some_and(|id| id != evt.id){
-                    panic!("Cannot end a gesture that hasn't even begun (that's mean)")
-                } 
-
-                let param_ptr = match q_nih_param.get(evt.id) {
-                    Ok(nih) => nih.param,
-                    Err(_) => panic!("Error while retreiving the NIH Param component.
-                        \nMake sure it is present in the entity")
-                };
-
-                unsafe { ctx.0.raw_end_set_parameter(param_ptr) };
+                if !gestures.contains(param_ptr, source) {
+                    report(ParamGestureError::NotStarted { target: evt.target.clone() });
+                    continue;
+                }
 
-                current.0 = None;
+                let sources = gestures.sources.get_mut(&param_ptr).unwrap();
+                sources.retain(|&other| other != source);
+                if sources.is_empty() {
+                    gestures.sources.remove(&param_ptr);
+                    gestures.open.end(&*ctx.0, param_ptr);
+                }
             },
         }
     }
-}
\ No newline at end of file
+}
+
+/// Ends the gestures held by entities that were despawned or lost their [`NIHParam`] in the middle
+/// of a gesture, since some hosts would otherwise consider those parameters to be touched forever.
+fn dangling_gesture_system(
+    ctx: Res<NIHContext>,
+    mut gestures: ResMut<NIHGestures>,
+    mut removed: RemovedComponents<NIHParam>,
+) {
+    let NIHGestures { sources, open } = &mut *gestures;
+    for entity in removed.read() {
+        let source = GestureSource::Entity(entity);
+        sources.retain(|&param_ptr, param_sources| {
+            param_sources.retain(|&other| other != source);
+            if param_sources.is_empty() {
+                open.end(&*ctx.0, param_ptr);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nih_plug::{
+        prelude::{FloatParam, FloatRange, Param, PluginApi},
+        wrapper::state::PluginState,
+    };
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Call {
+        Begin,
+        Set(f32),
+        End,
+    }
+
+    /// Records the gestures the host would see.
+    #[derive(Default)]
+    struct MockContext {
+        calls: Mutex<Vec<Call>>,
+    }
+
+    impl GuiContext for MockContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Clap
+        }
+
+        fn request_resize(&self) -> bool {
+            false
+        }
+
+        unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {
+            self.calls.lock().unwrap().push(Call::Begin);
+        }
+
+        unsafe fn raw_set_parameter_normalized(&self, _param: ParamPtr, normalized: f32) {
+            self.calls.lock().unwrap().push(Call::Set(normalized));
+        }
+
+        unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {
+            self.calls.lock().unwrap().push(Call::End);
+        }
+
+        fn get_state(&self) -> PluginState {
+            unreachable!("param_system never touches plugin state")
+        }
+
+        fn set_state(&self, _state: PluginState) {
+            unreachable!("param_system never touches plugin state")
+        }
+    }
+
+    struct Harness {
+        app: App,
+        ctx: Arc<MockContext>,
+        // The app refers to this parameter through a `ParamPtr`, so it needs to outlive the app
+        param: Box<FloatParam>,
+    }
+
+    impl Harness {
+        fn new() -> Self {
+            let ctx = Arc::new(MockContext::default());
+            let param = Box::new(FloatParam::new(
+                "Gain",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            ));
+
+            let mut app = App::new();
+            app.insert_resource(NIHContext::new(ctx.clone()))
+                .init_resource::<NIHParamConfig>()
+                .init_resource::<NIHGestures>()
+                .init_resource::<NIHParamEntities>()
+                .insert_resource(NIHParams::new(HashMap::from([(
+                    String::from("gain"),
+                    param.as_ptr(),
+                )])))
+                .add_event::<ParamEvent>()
+                .add_event::<ParamGestureError>()
+                .add_systems(Update, param_system);
+
+            Self { app, ctx, param }
+        }
+
+        fn ptr(&self) -> ParamPtr {
+            self.param.as_ptr()
+        }
+
+        fn spawn(&mut self) -> Entity {
+            let ptr = self.ptr();
+            self.app.world_mut().spawn(NIHParam::new(ptr)).id()
+        }
+
+        fn run(&mut self, events: impl IntoIterator<Item = ParamEvent>) {
+            for event in events {
+                self.app.world_mut().send_event(event);
+            }
+            self.app.update();
+        }
+
+        fn calls(&self) -> Vec<Call> {
+            std::mem::take(&mut *self.ctx.calls.lock().unwrap())
+        }
+
+        fn errors(&mut self) -> Vec<ParamGestureError> {
+            self.app
+                .world_mut()
+                .resource_mut::<Events<ParamGestureError>>()
+                .drain()
+                .collect()
+        }
+    }
+
+    #[test]
+    fn entity_gesture() {
+        let mut harness = Harness::new();
+        let entity = harness.spawn();
+
+        harness.run([
+            ParamEvent::begin(entity),
+            ParamEvent::set(entity, 0.25),
+            ParamEvent::end(entity),
+        ]);
+        assert_eq!(harness.calls(), [Call::Begin, Call::Set(0.25), Call::End]);
+        assert!(harness.errors().is_empty());
+        assert!(!harness.app.world().resource::<NIHGestures>().is_active(harness.ptr()));
+    }
+
+    #[test]
+    fn shared_gesture_ends_with_the_last_source() {
+        let mut harness = Harness::new();
+        let first = harness.spawn();
+        let second = harness.spawn();
+
+        harness.run([
+            ParamEvent::begin(first),
+            ParamEvent::begin(second),
+            ParamEvent::set(first, 0.25),
+            ParamEvent::end(first),
+        ]);
+        assert_eq!(harness.calls(), [Call::Begin, Call::Set(0.25)]);
+        assert_eq!(
+            harness.app.world().resource::<NIHGestures>().sources(harness.ptr()),
+            [GestureSource::Entity(second)]
+        );
+
+        harness.run([ParamEvent::set(second, 0.75), ParamEvent::end(second)]);
+        assert_eq!(harness.calls(), [Call::Set(0.75), Call::End]);
+        assert!(harness.errors().is_empty());
+    }
+
+    #[test]
+    fn direct_sets_outside_a_gesture_are_one_off_changes() {
+        let mut harness = Harness::new();
+        let ptr = harness.ptr();
+
+        harness.run([ParamEvent::set_by_id("gain", 0.25), ParamEvent::set_param(ptr, 0.75)]);
+        assert_eq!(
+            harness.calls(),
+            [
+                Call::Begin,
+                Call::Set(0.25),
+                Call::End,
+                Call::Begin,
+                Call::Set(0.75),
+                Call::End,
+            ]
+        );
+        assert!(harness.errors().is_empty());
+    }
+
+    #[test]
+    fn direct_gesture_by_id_and_ptr_share_a_source() {
+        let mut harness = Harness::new();
+        let ptr = harness.ptr();
+
+        harness.run([
+            ParamEvent::begin_by_id("gain"),
+            ParamEvent::set_param(ptr, 0.25),
+            ParamEvent::end_param(ptr),
+        ]);
+        assert_eq!(harness.calls(), [Call::Begin, Call::Set(0.25), Call::End]);
+        assert!(harness.errors().is_empty());
+    }
+
+    #[test]
+    fn stray_entity_set_is_recovered() {
+        let mut harness = Harness::new();
+        let entity = harness.spawn();
+
+        harness.run([ParamEvent::set(entity, 0.25)]);
+        assert_eq!(harness.calls(), [Call::Begin, Call::Set(0.25), Call::End]);
+        assert_eq!(
+            harness.errors(),
+            [ParamGestureError::NotStarted { target: ParamTarget::Entity(entity) }]
+        );
+    }
+
+    #[test]
+    fn invalid_gestures_are_reported_and_ignored() {
+        let mut harness = Harness::new();
+        let entity = harness.spawn();
+        let missing = harness.app.world_mut().spawn_empty().id();
+
+        harness.run([
+            ParamEvent::end(entity),
+            ParamEvent::begin(entity),
+            ParamEvent::begin(entity),
+            ParamEvent::begin(missing),
+            ParamEvent::begin_by_id("unknown"),
+        ]);
+        assert_eq!(harness.calls(), [Call::Begin]);
+        assert_eq!(
+            harness.errors(),
+            [
+                ParamGestureError::NotStarted { target: ParamTarget::Entity(entity) },
+                ParamGestureError::AlreadyStarted { target: ParamTarget::Entity(entity) },
+                ParamGestureError::MissingParam { entity: missing },
+                ParamGestureError::UnknownId { id: String::from("unknown") },
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_id_hashes_are_not_resolved() {
+        let harness = Harness::new();
+        // These two IDs share the same 32-bit FNV-1a hash
+        let params = NIHParams::new(HashMap::from([
+            (String::from("costarring"), harness.ptr()),
+            (String::from("liquid"), harness.ptr()),
+            (String::from("gain"), harness.ptr()),
+        ]));
+
+        let id_hash = param_id_hash("liquid");
+        assert_eq!(param_id_hash("costarring"), id_hash);
+        assert!(params.is_colliding_hash(id_hash));
+        assert!(params.entry_by_hash(id_hash).is_none());
+        assert_eq!(
+            params.entry_by_hash(param_id_hash("gain")).map(|entry| entry.id.as_str()),
+            Some("gain")
+        );
+    }
+
+    #[test]
+    fn hand_made_params_are_sorted_by_id() {
+        let harness = Harness::new();
+        let params = NIHParams::new(HashMap::from([
+            (String::from("mix"), harness.ptr()),
+            (String::from("gain"), harness.ptr()),
+        ]));
+
+        let ids: Vec<&str> = params.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["gain", "mix"]);
+        assert_eq!(params.map(), HashMap::from([("gain", harness.ptr()), ("mix", harness.ptr())]));
+    }
+
+    #[test]
+    fn entities_are_indexed_by_param() {
+        let mut harness = Harness::new();
+        let ptr = harness.ptr();
+        let first = harness.spawn();
+        let second = harness.spawn();
+
+        let index = harness.app.world().resource::<NIHParamEntities>();
+        assert_eq!(index.entities(ptr), [first, second]);
+
+        harness.app.world_mut().despawn(first);
+        let index = harness.app.world().resource::<NIHParamEntities>();
+        assert_eq!(index.entities(ptr), [second]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn strict_gestures_panic() {
+        let mut harness = Harness::new();
+        harness.app.insert_resource(NIHParamConfig { strict_gestures: true });
+        let entity = harness.spawn();
+
+        harness.run([ParamEvent::end(entity)]);
+    }
+}
@@ -0,0 +1,205 @@
+//! Versioned (de)serialization for [`BevyState`]. The state is serialized through
+//! [`PersistedBevyState`], and older payloads are upgraded by the migrations in [`MIGRATIONS`]
+//! before they are deserialized.
+
+use std::sync::atomic::{AtomicBool, AtomicU32};
+use std::sync::RwLock;
+
+use crossbeam::atomic::AtomicCell;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::BevyState;
+
+/// The version of the persisted format written by this version of the crate. Bump this and add a
+/// migration to [`MIGRATIONS`] whenever [`PersistedBevyState`] changes in a way that old payloads
+/// can't be read as-is.
+pub(crate) const CURRENT_VERSION: u32 = 1;
+
+pub(crate) const DEFAULT_ZOOM: f32 = 1.0;
+
+/// Upgrades a payload from version `n` to version `n + 1`, where `n` is the index in this list.
+const MIGRATIONS: [fn(&mut Value); CURRENT_VERSION as usize] = [migrate_v0_to_v1];
+
+#[derive(Serialize, Deserialize)]
+struct PersistedBevyState {
+    /// Payloads written before the format was versioned don't have this field.
+    #[serde(default)]
+    version: u32,
+    size: (u32, u32),
+    #[serde(default)]
+    min_size: Option<(u32, u32)>,
+    #[serde(default)]
+    max_size: Option<(u32, u32)>,
+    #[serde(default)]
+    aspect_ratio: Option<f32>,
+    #[serde(default = "default_zoom")]
+    zoom: f32,
+    #[serde(default)]
+    ui_state: Option<Value>,
+}
+
+fn default_zoom() -> f32 {
+    DEFAULT_ZOOM
+}
+
+/// Version 0 payloads only ever stored the size. Everything that was added since then has a
+/// default value, so nothing needs to change besides the version number.
+fn migrate_v0_to_v1(_payload: &mut Value) {}
+
+/// Run all migrations needed to bring `payload` up to [`CURRENT_VERSION`].
+fn migrate(payload: &mut Value) -> Result<(), String> {
+    let Some(object) = payload.as_object() else {
+        return Err(String::from("the editor state is not a JSON object"));
+    };
+
+    let version = match object.get("version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("invalid editor state version {version}"))?,
+        None => 0,
+    };
+
+    if version > CURRENT_VERSION {
+        // Newer payloads can still be read on a best-effort basis since unknown fields are ignored
+        nih_plug::nih_warn!(
+            "The editor state was saved with a newer version ({version}) of nih_plug_bevy, some \
+             settings may be lost"
+        );
+        return Ok(());
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(payload);
+    }
+    if let Some(object) = payload.as_object_mut() {
+        object.insert(String::from("version"), Value::from(CURRENT_VERSION));
+    }
+
+    Ok(())
+}
+
+impl Serialize for BevyState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PersistedBevyState {
+            version: CURRENT_VERSION,
+            size: self.size.load(),
+            min_size: self.min_size.load(),
+            max_size: self.max_size.load(),
+            aspect_ratio: self.aspect_ratio.load(),
+            zoom: self.zoom.load(),
+            ui_state: self.ui_state.read().unwrap().clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BevyState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut payload = Value::deserialize(deserializer)?;
+        migrate(&mut payload).map_err(D::Error::custom)?;
+        let persisted: PersistedBevyState =
+            serde_json::from_value(payload).map_err(D::Error::custom)?;
+
+        Ok(BevyState {
+            size: AtomicCell::new(persisted.size),
+            min_size: AtomicCell::new(persisted.min_size),
+            max_size: AtomicCell::new(persisted.max_size),
+            aspect_ratio: AtomicCell::new(persisted.aspect_ratio),
+            zoom: AtomicCell::new(persisted.zoom),
+            ui_state: RwLock::new(persisted.ui_state),
+            ui_state_version: AtomicU32::new(0),
+            open: AtomicBool::new(false),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nih_plug::params::persist::PersistentField;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unversioned_payload_is_migrated() {
+        let mut payload = json!({ "size": [800, 600] });
+        migrate(&mut payload).unwrap();
+        assert_eq!(payload["version"], json!(CURRENT_VERSION));
+
+        let state: BevyState = serde_json::from_value(json!({ "size": [800, 600] })).unwrap();
+        assert_eq!(state.size(), (800, 600));
+        assert_eq!(state.zoom(), DEFAULT_ZOOM);
+        assert_eq!(state.ui_state::<Value>(), None);
+    }
+
+    #[test]
+    fn current_version_round_trips() {
+        let state = BevyState::from_size(640, 480);
+        state.set_zoom(1.5);
+        state.set_ui_state(&json!({ "tab": "mixer" }));
+
+        let payload = serde_json::to_value(&*state).unwrap();
+        assert_eq!(payload["version"], json!(CURRENT_VERSION));
+
+        let restored: BevyState = serde_json::from_value(payload).unwrap();
+        assert_eq!(restored.size(), (640, 480));
+        assert_eq!(restored.zoom(), 1.5);
+        assert_eq!(restored.ui_state::<Value>(), Some(json!({ "tab": "mixer" })));
+    }
+
+    #[test]
+    fn size_constraints_round_trip() {
+        let state = BevyState::from_size(800, 600)
+            .with_min_size(400, 300)
+            .with_max_size(1600, 1200)
+            .with_aspect_ratio(4.0 / 3.0);
+
+        let payload = serde_json::to_value(&*state).unwrap();
+        let restored: BevyState = serde_json::from_value(payload).unwrap();
+        assert_eq!(restored.min_size(), Some((400, 300)));
+        assert_eq!(restored.max_size(), Some((1600, 1200)));
+        assert_eq!(restored.aspect_ratio(), Some(4.0 / 3.0));
+    }
+
+    #[test]
+    fn restored_constraints_are_applied() {
+        let state = BevyState::from_size(800, 600).with_min_size(400, 300);
+        let restored: BevyState = serde_json::from_value(json!({
+            "version": 1,
+            "size": [500, 400],
+            "min_size": [640, 480],
+        }))
+        .unwrap();
+
+        // Constraints missing from the payload keep the plugin's values
+        state.set(restored);
+        assert_eq!(state.min_size(), Some((640, 480)));
+        assert_eq!(state.max_size(), None);
+        assert_eq!(state.size(), (640, 480));
+    }
+
+    #[test]
+    fn newer_version_is_read_on_a_best_effort_basis() {
+        let payload = json!({
+            "version": CURRENT_VERSION + 1,
+            "size": [1024, 768],
+            "zoom": 2.0,
+            "added_in_a_later_version": true,
+        });
+
+        let state: BevyState = serde_json::from_value(payload).unwrap();
+        assert_eq!(state.size(), (1024, 768));
+        assert_eq!(state.zoom(), 2.0);
+    }
+
+    #[test]
+    fn invalid_payloads_are_rejected() {
+        assert!(serde_json::from_value::<BevyState>(json!([800, 600])).is_err());
+        assert!(
+            serde_json::from_value::<BevyState>(json!({ "version": "one", "size": [800, 600] }))
+                .is_err()
+        );
+    }
+}
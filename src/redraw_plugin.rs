@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButtonInput, MouseWheel},
+    },
+    prelude::*,
+    render::{Extract, ExtractSchedule, Render, RenderApp, RenderSet},
+    time::TimeSystem,
+    window::{CursorMoved, RequestRedraw, WindowResized},
+};
+
+use crate::{
+    param_plugin::{ParamModulationChanged, ParamValueChanged, ParamsReloaded},
+    window_plugin::{ResizeRequest, ScaleFactorChanged, ZoomRequest},
+};
+
+/// The number of frames that are rendered after something triggered a redraw, since UI layout and
+/// text updates can take a couple of frames to settle.
+const REDRAW_FRAMES: u32 = 3;
+
+//<========== Resources ==========>//
+
+/// When the editor renders a new frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Render every frame.
+    #[default]
+    Continuous,
+    /// Only render when input arrives, a parameter changes, the UI changes, an entity with
+    /// [`NIHAnimating`] exists, or a [`RequestRedraw`] event is sent.
+    ///
+    /// This only skips rendering. The app's own systems, including the ones in `Update`, still run
+    /// every frame, since whether a frame gets rendered is only known once they have run. Systems
+    /// that do expensive work every frame, like polling the audio thread and drawing the result,
+    /// can use [`should_update()`] as a run condition to at least skip the frames the FPS cap
+    /// would drop.
+    Reactive,
+}
+
+/// How and how often [`NIHRedrawPlugin`] renders. By default every frame is rendered without an
+/// FPS cap. This is read every frame, so it can also be changed while the editor is open, e.g. to
+/// render continuously only while an animation plays.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct NIHRedrawConfig {
+    pub mode: RedrawMode,
+    /// The maximum number of frames rendered per second, if any.
+    pub fps_cap: Option<f32>,
+}
+
+/// What [`NIHRedrawPlugin`] decided for the current frame, see [`should_update()`].
+#[derive(Resource)]
+pub struct NIHRedrawState {
+    frames_left: u32,
+    last_render: Option<Duration>,
+    /// Whether the FPS cap allows rendering the current frame, updated at the start of the frame.
+    frame_due: bool,
+    render: bool,
+}
+
+impl Default for NIHRedrawState {
+    fn default() -> Self {
+        Self {
+            frames_left: REDRAW_FRAMES,
+            last_render: None,
+            frame_due: true,
+            render: true,
+        }
+    }
+}
+
+/// Whether the render app should render the current frame, extracted from [`NIHRedrawState`].
+#[derive(Resource)]
+struct RenderThisFrame(bool);
+
+impl Default for RenderThisFrame {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+//<========== Plugin ==========>//
+
+/// Skips rendering frames in which nothing changed when [`NIHRedrawConfig::mode`] is set to
+/// [`RedrawMode::Reactive`], and applies the FPS cap. The app's systems still run every frame, see
+/// [`should_update()`].
+pub struct NIHRedrawPlugin;
+
+impl Plugin for NIHRedrawPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<NIHRedrawConfig>()
+            .init_resource::<NIHRedrawState>()
+            .add_systems(First, frame_due_system.after(TimeSystem))
+            .add_systems(Last, redraw_system);
+    }
+
+    // The render app only exists once the user's plugins have been added
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        // Assets are still prepared and the render world is still cleaned up, so nothing gets lost
+        // in the frames that aren't rendered
+        render_app
+            .init_resource::<RenderThisFrame>()
+            .add_systems(ExtractSchedule, extract_redraw);
+        for set in [
+            RenderSet::ManageViews,
+            RenderSet::Queue,
+            RenderSet::PhaseSort,
+            RenderSet::Prepare,
+            RenderSet::Render,
+        ] {
+            render_app.configure_sets(Render, set.run_if(should_render));
+        }
+    }
+}
+
+//<========== Components ==========>//
+
+/// Marks an entity that is currently animating. The editor renders every frame while any entity
+/// has this component.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct NIHAnimating;
+
+//<========== Run conditions ==========>//
+
+/// A run condition that is true when the current frame can be rendered as far as
+/// [`NIHRedrawConfig::fps_cap`] is concerned, and always true without an FPS cap. Systems that
+/// redraw something every frame can use this to skip frames that would never be shown, e.g.
+/// `app.add_systems(Update, draw_waveform.run_if(should_update))`. With
+/// [`RedrawMode::Reactive`] a due frame is still only rendered if something changed.
+///
+/// Keep in mind that systems skipped this way don't see the frames in between, so anything that
+/// depends on `Time::delta()` should measure the time since its last run instead.
+pub fn should_update(state: Option<Res<NIHRedrawState>>) -> bool {
+    state.is_none_or(|state| state.frame_due)
+}
+
+//<========== Systems ==========>//
+
+#[derive(SystemParam)]
+struct RedrawTriggers<'w, 's> {
+    cursor_moved: EventReader<'w, 's, CursorMoved>,
+    mouse_buttons: EventReader<'w, 's, MouseButtonInput>,
+    mouse_wheel: EventReader<'w, 's, MouseWheel>,
+    keyboard: EventReader<'w, 's, KeyboardInput>,
+    window_resized: EventReader<'w, 's, WindowResized>,
+    redraw_requests: EventReader<'w, 's, RequestRedraw>,
+    param_values: EventReader<'w, 's, ParamValueChanged>,
+    param_modulation: EventReader<'w, 's, ParamModulationChanged>,
+    params_reloaded: EventReader<'w, 's, ParamsReloaded>,
+    resize_requests: EventReader<'w, 's, ResizeRequest>,
+    zoom_requests: EventReader<'w, 's, ZoomRequest>,
+    scale_factor_changes: EventReader<'w, 's, ScaleFactorChanged>,
+}
+
+impl RedrawTriggers<'_, '_> {
+    /// Whether any of the events were sent since the last check.
+    fn triggered(&mut self) -> bool {
+        let triggered = [
+            !self.cursor_moved.is_empty(),
+            !self.mouse_buttons.is_empty(),
+            !self.mouse_wheel.is_empty(),
+            !self.keyboard.is_empty(),
+            !self.window_resized.is_empty(),
+            !self.redraw_requests.is_empty(),
+            !self.param_values.is_empty(),
+            !self.param_modulation.is_empty(),
+            !self.params_reloaded.is_empty(),
+            !self.resize_requests.is_empty(),
+            !self.zoom_requests.is_empty(),
+            !self.scale_factor_changes.is_empty(),
+        ]
+        .contains(&true);
+
+        self.cursor_moved.clear();
+        self.mouse_buttons.clear();
+        self.mouse_wheel.clear();
+        self.keyboard.clear();
+        self.window_resized.clear();
+        self.redraw_requests.clear();
+        self.param_values.clear();
+        self.param_modulation.clear();
+        self.params_reloaded.clear();
+        self.resize_requests.clear();
+        self.zoom_requests.clear();
+        self.scale_factor_changes.clear();
+
+        triggered
+    }
+}
+
+type VisualChange = Or<(
+    Changed<Node>,
+    Changed<Style>,
+    Changed<Text>,
+    Changed<BackgroundColor>,
+    Changed<BorderColor>,
+    Changed<Transform>,
+    Changed<Visibility>,
+)>;
+
+fn frame_due_system(
+    config: Res<NIHRedrawConfig>,
+    time: Res<Time<Real>>,
+    mut state: ResMut<NIHRedrawState>,
+) {
+    let now = time.elapsed();
+    state.frame_due = match (config.fps_cap, state.last_render) {
+        (Some(fps), Some(last_render)) if fps > 0.0 => {
+            now.saturating_sub(last_render) >= Duration::from_secs_f32(fps.recip())
+        }
+        _ => true,
+    };
+}
+
+fn redraw_system(
+    config: Res<NIHRedrawConfig>,
+    time: Res<Time<Real>>,
+    mut state: ResMut<NIHRedrawState>,
+    mut triggers: RedrawTriggers,
+    q_animating: Query<(), With<NIHAnimating>>,
+    q_changed: Query<(), VisualChange>,
+) {
+    // The events need to be consumed either way
+    let triggered = triggers.triggered();
+    let needs_redraw = match config.mode {
+        RedrawMode::Continuous => true,
+        RedrawMode::Reactive => triggered || !q_animating.is_empty() || !q_changed.is_empty(),
+    };
+    if needs_redraw {
+        state.frames_left = REDRAW_FRAMES;
+    }
+
+    state.render = state.frames_left > 0 && state.frame_due;
+    if state.render {
+        state.frames_left -= 1;
+        state.last_render = Some(time.elapsed());
+    }
+}
+
+fn extract_redraw(
+    mut render_this_frame: ResMut<RenderThisFrame>,
+    state: Extract<Res<NIHRedrawState>>,
+) {
+    render_this_frame.0 = state.render;
+}
+
+fn should_render(render_this_frame: Res<RenderThisFrame>) -> bool {
+    render_this_frame.0
+}
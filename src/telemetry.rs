@@ -0,0 +1,134 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, window::RequestRedraw};
+use triple_buffer::{Input, Output, TripleBuffer};
+
+/// Create a channel for sending structured data, like meter levels or waveforms, from the audio
+/// thread to the editor. The [`TelemetrySender`] is kept by the plugin and written to from
+/// `process()` without blocking. The [`TelemetryReceiver`] is registered with
+/// [`BevyEditorBuilder::with_telemetry()`][crate::BevyEditorBuilder::with_telemetry()], or with a
+/// [`NIHTelemetryPlugin`], and shows up as a [`NIHTelemetry<T>`] resource in the editor.
+///
+/// This is a triple buffer, so the editor only ever sees the latest value that was published. For
+/// values that own heap memory, like a `Vec` of samples, fill
+/// [`TelemetrySender::input_buffer()`] in place and then [`publish()`][TelemetrySender::publish()]
+/// it so the audio thread never allocates or deallocates.
+pub fn telemetry_channel<T>(initial: T) -> (TelemetrySender<T>, TelemetryReceiver<T>)
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let (input, output) = TripleBuffer::new(&initial).split();
+
+    (
+        TelemetrySender(input),
+        TelemetryReceiver(Arc::new(Mutex::new(output))),
+    )
+}
+
+/// The audio thread's end of a [`telemetry_channel()`].
+pub struct TelemetrySender<T: Send>(Input<T>);
+
+impl<T: Send> TelemetrySender<T> {
+    /// Publish a new value. This replaces the value in the input buffer, so keep in mind that
+    /// dropping a value that owns heap memory deallocates on the audio thread. Use
+    /// [`input_buffer()`][Self::input_buffer()] and [`publish()`][Self::publish()] for those.
+    pub fn write(&mut self, value: T) {
+        self.0.write(value);
+    }
+
+    /// The buffer that will be sent with the next [`publish()`][Self::publish()]. This can be
+    /// modified in place to avoid moving large values around. It is not cleared between
+    /// publishes, but it doesn't necessarily hold the last published value either.
+    pub fn input_buffer(&mut self) -> &mut T {
+        self.0.input_buffer()
+    }
+
+    /// Send the contents of [`input_buffer()`][Self::input_buffer()] to the editor.
+    pub fn publish(&mut self) {
+        self.0.publish();
+    }
+}
+
+/// The editor's end of a [`telemetry_channel()`]. This can be cloned so it can be moved into the
+/// editor every time it's opened. The lock is only ever taken on the GUI thread.
+pub struct TelemetryReceiver<T: Send>(Arc<Mutex<Output<T>>>);
+
+impl<T: Send> Clone for TelemetryReceiver<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+//<========== Resources ==========>//
+
+/// The latest value received through a [`telemetry_channel()`]. This resource only changes when a
+/// new value has been published, so `Res<NIHTelemetry<T>>::is_changed()` can be used to only update
+/// visualizations when there's new data.
+#[derive(Resource)]
+pub struct NIHTelemetry<T: Send + Sync + 'static> {
+    value: T,
+}
+
+impl<T: Send + Sync + 'static> NIHTelemetry<T> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Send + Sync + 'static> std::ops::Deref for NIHTelemetry<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+#[derive(Resource)]
+struct NIHTelemetryReceiver<T: Send>(TelemetryReceiver<T>);
+
+//<========== Plugin ==========>//
+
+/// Makes the values sent through a [`telemetry_channel()`] available as a [`NIHTelemetry<T>`]
+/// resource. New values also wake up the editor when it only redraws on demand.
+///
+/// Bevy only allows adding this plugin once for every `T`, since there's only one
+/// [`NIHTelemetry<T>`] resource per type.
+pub struct NIHTelemetryPlugin<T: Send> {
+    receiver: TelemetryReceiver<T>,
+}
+
+impl<T: Send> NIHTelemetryPlugin<T> {
+    pub fn new(receiver: TelemetryReceiver<T>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl<T> Plugin for NIHTelemetryPlugin<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        let value = self.receiver.0.lock().unwrap().read().clone();
+
+        app
+            .insert_resource(NIHTelemetry { value })
+            .insert_resource(NIHTelemetryReceiver(self.receiver.clone()))
+            .add_systems(PreUpdate, telemetry_system::<T>);
+    }
+}
+
+//<========== Systems ==========>//
+
+fn telemetry_system<T>(
+    receiver: Res<NIHTelemetryReceiver<T>>,
+    mut telemetry: ResMut<NIHTelemetry<T>>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) where
+    T: Clone + Send + Sync + 'static,
+{
+    let mut output = receiver.0 .0.lock().unwrap();
+    if output.update() {
+        telemetry.value.clone_from(output.read());
+        redraw_events.send(RequestRedraw);
+    }
+}
@@ -0,0 +1,86 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{lifecycle_plugin::EditorClose, window_plugin::NIHBevyState};
+
+//<========== Resources ==========>//
+
+/// Remembers which version of the persisted UI state the `T` resource was loaded from.
+#[derive(Resource)]
+struct UiStateVersion<T> {
+    version: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+//<========== Plugin ==========>//
+
+/// Makes a custom UI state struct, like the selected tab or a collapsed panel, available as a
+/// `T` resource while the editor is open. The resource is loaded from the editor's
+/// [`BevyState`][crate::BevyState], written back whenever it changes and when the editor closes,
+/// and reloaded when the host restores the plugin's state. This way it is persisted through the
+/// `#[persist]` field holding the `BevyState`.
+///
+/// Add this plugin in the editor's build function.
+pub struct NIHUiStatePlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for NIHUiStatePlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for NIHUiStatePlugin<T>
+where
+    T: Resource + Serialize + DeserializeOwned + Default,
+{
+    fn build(&self, app: &mut App) {
+        let (ui_state, version) = match app.world().get_resource::<NIHBevyState>() {
+            Some(bevy_state) => (
+                bevy_state.state().ui_state::<T>().unwrap_or_default(),
+                bevy_state.state().ui_state_version(),
+            ),
+            None => (T::default(), 0),
+        };
+
+        app
+            .insert_resource(ui_state)
+            .insert_resource(UiStateVersion::<T> {
+                version,
+                _marker: PhantomData,
+            })
+            .add_systems(PreUpdate, reload_ui_state::<T>)
+            .add_systems(PostUpdate, store_ui_state::<T>.run_if(resource_changed::<T>))
+            .add_systems(EditorClose, store_ui_state::<T>);
+    }
+}
+
+//<========== Systems ==========>//
+
+fn reload_ui_state<T>(
+    bevy_state: Option<Res<NIHBevyState>>,
+    mut version: ResMut<UiStateVersion<T>>,
+    mut ui_state: ResMut<T>,
+) where
+    T: Resource + DeserializeOwned + Default,
+{
+    let Some(bevy_state) = bevy_state else {
+        return;
+    };
+
+    let current_version = bevy_state.state().ui_state_version();
+    if version.version != current_version {
+        version.version = current_version;
+        *ui_state = bevy_state.state().ui_state::<T>().unwrap_or_default();
+    }
+}
+
+fn store_ui_state<T>(bevy_state: Option<Res<NIHBevyState>>, ui_state: Res<T>)
+where
+    T: Resource + Serialize,
+{
+    if let Some(bevy_state) = bevy_state {
+        bevy_state.state().set_ui_state(&*ui_state);
+    }
+}
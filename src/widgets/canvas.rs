@@ -0,0 +1,139 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+/// Create an empty image for a widget that draws its contents on the CPU.
+pub(crate) fn new_canvas_image(images: &mut Assets<Image>) -> Handle<Image> {
+    images.add(Image::new_fill(
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    ))
+}
+
+/// The size in physical pixels a canvas image needs to cover a node without being stretched.
+pub(crate) fn canvas_size(node: &Node, scale_factor: f32) -> (u32, u32) {
+    let size = (node.size() * scale_factor).round();
+    (size.x.max(1.) as u32, size.y.max(1.) as u32)
+}
+
+/// A view into an RGBA image that can be drawn on. The origin is the top left corner.
+pub(crate) struct Canvas<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> Canvas<'a> {
+    /// Resize the image if needed, and start drawing on it.
+    pub(crate) fn new(image: &'a mut Image, (width, height): (u32, u32)) -> Self {
+        if image.width() != width || image.height() != height {
+            image.resize(Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            });
+        }
+
+        Self {
+            data: &mut image.data,
+            width,
+            height,
+        }
+    }
+
+    pub(crate) fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub(crate) fn clear(&mut self, color: Color) {
+        let color = color.to_srgba().to_u8_array();
+        for pixel in self.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// Blend a color over a single pixel. Pixels outside of the canvas are ignored.
+    pub(crate) fn blend(&mut self, x: i32, y: i32, color: Srgba) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        let pixel = &mut self.data[index..index + 4];
+        let alpha = color.alpha;
+        for (channel, value) in pixel
+            .iter_mut()
+            .zip([color.red, color.green, color.blue])
+        {
+            *channel = (*channel as f32 * (1. - alpha) + value * 255. * alpha).round() as u8;
+        }
+        pixel[3] = pixel[3].max((alpha * 255.).round() as u8);
+    }
+
+    pub(crate) fn horizontal_line(&mut self, y: f32, color: Color) {
+        let color = color.to_srgba();
+        let y = y.round() as i32;
+        for x in 0..self.width as i32 {
+            self.blend(x, y, color);
+        }
+    }
+
+    pub(crate) fn vertical_line(&mut self, x: f32, color: Color) {
+        let color = color.to_srgba();
+        let x = x.round() as i32;
+        for y in 0..self.height as i32 {
+            self.blend(x, y, color);
+        }
+    }
+
+    /// Fill column `x` from `y` down to the bottom.
+    pub(crate) fn fill_below(&mut self, x: i32, y: f32, color: Color) {
+        let color = color.to_srgba();
+        for y in (y.round().max(0.) as i32)..self.height as i32 {
+            self.blend(x, y, color);
+        }
+    }
+
+    /// Draw a line through the points, `thickness` pixels wide.
+    pub(crate) fn polyline(
+        &mut self,
+        points: impl IntoIterator<Item = Vec2>,
+        thickness: f32,
+        color: Color,
+    ) {
+        let color = color.to_srgba();
+        let radius = ((thickness - 1.) / 2.).max(0.).round() as i32;
+
+        let mut points = points.into_iter();
+        let Some(mut previous) = points.next() else {
+            return;
+        };
+        for point in points {
+            let steps = (point - previous).abs().max_element().ceil().max(1.);
+            for step in 0..steps as u32 {
+                let position = previous.lerp(point, step as f32 / steps).round();
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        self.blend(position.x as i32 + dx, position.y as i32 + dy, color);
+                    }
+                }
+            }
+            previous = point;
+        }
+    }
+}
@@ -0,0 +1,697 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use bevy::prelude::*;
+use nih_plug::{buffer::Buffer, prelude::AtomicF32, util};
+
+use super::smoothing;
+
+/// The default thickness of a single channel's bar in logical pixels.
+const DEFAULT_BAR_THICKNESS: f32 = 8.;
+/// The default length of a meter in logical pixels.
+const DEFAULT_METER_LENGTH: f32 = 160.;
+/// The size of the clip indicator along the meter's axis.
+const CLIP_INDICATOR_SIZE: f32 = 4.;
+/// The thickness of the peak hold marker.
+const HOLD_MARKER_SIZE: f32 = 2.;
+/// The space reserved for the dB scale's labels next to the bars.
+const SCALE_SIZE: f32 = 24.;
+const SCALE_FONT_SIZE: f32 = 9.;
+/// The gap between the bars and the scale.
+const GAP: f32 = 2.;
+
+/// The time it takes for a VU meter to reach 99% of a steady signal's level is 300 ms, which
+/// makes for a time constant of `0.3 / ln(100)` seconds.
+const VU_TIME_CONSTANT: f32 = 0.065;
+
+//<========== Audio thread ==========>//
+
+#[derive(Debug, Default)]
+struct ChannelAccumulator {
+    peak: AtomicF32,
+    sum_squares: AtomicF32,
+    num_samples: AtomicU32,
+}
+
+/// The levels of one or more channels, shared between the audio thread and a single
+/// [`LevelMeter`]. The plugin feeds its output to this from `process()`, and the meter drains
+/// whatever arrived since its previous frame, so no peaks are lost between frames. This doesn't
+/// block or allocate.
+///
+/// Draining resets the source, so every meter needs a source of its own. Meters sharing a source
+/// would each only see part of the signal.
+///
+/// Nobody sees the levels while the editor is closed, so `process()` can skip pushing them when
+/// [`BevyState::is_open()`][crate::BevyState::is_open()] is false. The meter doesn't mind the gap.
+#[derive(Debug, Clone)]
+pub struct LevelMeterSource {
+    channels: Arc<[ChannelAccumulator]>,
+}
+
+impl LevelMeterSource {
+    pub fn new(num_channels: usize) -> Self {
+        Self {
+            channels: (0..num_channels)
+                .map(|_| ChannelAccumulator::default())
+                .collect(),
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Add a block of samples for a single channel. Channels the source wasn't created with are
+    /// ignored.
+    pub fn push_samples(&self, channel: usize, samples: &[f32]) {
+        let Some(accumulator) = self.channels.get(channel) else {
+            return;
+        };
+
+        let (peak, sum_squares) = samples
+            .iter()
+            .fold((0.0f32, 0.0f32), |(peak, sum_squares), sample| {
+                (peak.max(sample.abs()), sum_squares + sample * sample)
+            });
+
+        accumulator.peak.fetch_max(peak, Ordering::Relaxed);
+        accumulator
+            .sum_squares
+            .fetch_add(sum_squares, Ordering::Relaxed);
+        accumulator
+            .num_samples
+            .fetch_add(samples.len() as u32, Ordering::Relaxed);
+    }
+
+    /// Add every channel in a buffer.
+    pub fn push_buffer(&self, buffer: &Buffer) {
+        for (channel, samples) in buffer.as_slice_immutable().iter().enumerate() {
+            self.push_samples(channel, samples);
+        }
+    }
+
+    /// The peak, the sum of the squared samples, and the number of samples since the last call,
+    /// from any meter.
+    fn take(&self, channel: usize) -> (f32, f32, u32) {
+        let Some(accumulator) = self.channels.get(channel) else {
+            return (0., 0., 0);
+        };
+
+        (
+            accumulator.peak.swap(0., Ordering::Relaxed),
+            accumulator.sum_squares.swap(0., Ordering::Relaxed),
+            accumulator.num_samples.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+//<========== Components ==========>//
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MeterOrientation {
+    Horizontal,
+    #[default]
+    Vertical,
+}
+
+/// How a [`LevelMeter`] responds to the signal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MeterBallistics {
+    /// Jumps to the signal's sample peak instantly, and then falls at `release` decibels per
+    /// second.
+    Peak { release: f32 },
+    /// The RMS level, averaged with a time constant of `window` seconds.
+    Rms { window: f32 },
+    /// The RMS level with a VU meter's 300 ms integration time.
+    Vu,
+}
+
+impl Default for MeterBallistics {
+    fn default() -> Self {
+        Self::Peak { release: 20. }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MeterColors {
+    pub background: Color,
+    /// The bar's color below the warning level.
+    pub normal: Color,
+    /// The bar's color at or above the warning level.
+    pub warning: Color,
+    pub peak_hold: Color,
+    /// The clip indicator's color after the signal clipped.
+    pub clip: Color,
+    /// The clip indicator's color while the signal hasn't clipped.
+    pub clip_idle: Color,
+    pub scale: Color,
+}
+
+impl Default for MeterColors {
+    fn default() -> Self {
+        Self {
+            background: Color::srgb(0.1, 0.1, 0.1),
+            normal: Color::srgb(0.2, 0.8, 0.3),
+            warning: Color::srgb(0.95, 0.75, 0.1),
+            peak_hold: Color::srgb(0.9, 0.9, 0.9),
+            clip: Color::srgb(0.95, 0.15, 0.1),
+            clip_idle: Color::srgb(0.25, 0.1, 0.1),
+            scale: Color::srgba(1., 1., 1., 0.6),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct MeterChannel {
+    /// The displayed level in decibels.
+    level_db: f32,
+    /// The RMS and VU ballistics' state.
+    smoothed: f32,
+    /// The mean square of the samples received last, kept for frames in which nothing arrived.
+    mean_square: f32,
+    hold_db: f32,
+    /// The time in seconds since the peak hold was last raised.
+    hold_time: f32,
+    clipped: bool,
+}
+
+impl Default for MeterChannel {
+    fn default() -> Self {
+        Self {
+            level_db: util::MINUS_INFINITY_DB,
+            smoothed: 0.,
+            mean_square: 0.,
+            hold_db: util::MINUS_INFINITY_DB,
+            hold_time: 0.,
+            clipped: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct MeterChannelNodes {
+    fill: Entity,
+    hold: Entity,
+    clip: Entity,
+}
+
+/// A peak or RMS level meter with one bar per channel of its [`LevelMeterSource`], see
+/// [`LevelMeterBundle`]. Clicking the meter resets its clip indicators. Add [`LevelMeterPlugin`] to
+/// the editor to use this.
+#[derive(Component, Clone, Debug)]
+pub struct LevelMeter {
+    source: LevelMeterSource,
+    orientation: MeterOrientation,
+    ballistics: MeterBallistics,
+    min_db: f32,
+    max_db: f32,
+    warning_db: f32,
+    clip_db: f32,
+    peak_hold: Option<f32>,
+    scale_step: Option<f32>,
+    colors: MeterColors,
+
+    channels: Vec<MeterChannel>,
+    nodes: Vec<MeterChannelNodes>,
+}
+
+impl LevelMeter {
+    /// The meter drains `source` every frame, so don't share it with other meters.
+    pub fn new(source: LevelMeterSource) -> Self {
+        Self {
+            channels: vec![MeterChannel::default(); source.num_channels()],
+            nodes: Vec::new(),
+            source,
+            orientation: MeterOrientation::default(),
+            ballistics: MeterBallistics::default(),
+            min_db: -60.,
+            max_db: 6.,
+            warning_db: -6.,
+            clip_db: 0.,
+            peak_hold: Some(1.5),
+            scale_step: Some(12.),
+            colors: MeterColors::default(),
+        }
+    }
+
+    /// Defaults to [`MeterOrientation::Vertical`].
+    pub fn with_orientation(self, orientation: MeterOrientation) -> Self {
+        Self {
+            orientation,
+            ..self
+        }
+    }
+
+    /// Defaults to [`MeterBallistics::Peak`] with a release of 20 dB per second.
+    pub fn with_ballistics(self, ballistics: MeterBallistics) -> Self {
+        Self { ballistics, ..self }
+    }
+
+    /// The levels in decibels at the meter's start and end. Defaults to -60 dB and +6 dB.
+    pub fn with_range(self, min_db: f32, max_db: f32) -> Self {
+        Self {
+            min_db: min_db.min(max_db),
+            max_db: max_db.max(min_db),
+            ..self
+        }
+    }
+
+    /// The level in decibels from which the bars use the warning color. Defaults to -6 dB.
+    pub fn with_warning_level(self, warning_db: f32) -> Self {
+        Self { warning_db, ..self }
+    }
+
+    /// The sample peak in decibels that lights up the clip indicator. Defaults to 0 dB.
+    pub fn with_clip_level(self, clip_db: f32) -> Self {
+        Self { clip_db, ..self }
+    }
+
+    /// How long in seconds the highest level stays marked before it follows the level down again,
+    /// or `None` to disable the peak hold marker. Defaults to 1.5 seconds.
+    pub fn with_peak_hold(self, peak_hold: Option<f32>) -> Self {
+        Self { peak_hold, ..self }
+    }
+
+    /// The distance in decibels between the scale's labels, or `None` to hide the scale. Defaults to
+    /// 12 dB. The labels start at the meter's maximum.
+    pub fn with_scale(self, scale_step: Option<f32>) -> Self {
+        Self {
+            scale_step: scale_step.filter(|step| *step > 0.),
+            ..self
+        }
+    }
+
+    pub fn with_colors(self, colors: MeterColors) -> Self {
+        Self { colors, ..self }
+    }
+
+    pub fn orientation(&self) -> MeterOrientation {
+        self.orientation
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// A channel's displayed level in decibels.
+    pub fn level_db(&self, channel: usize) -> Option<f32> {
+        self.channels.get(channel).map(|channel| channel.level_db)
+    }
+
+    /// A channel's held peak level in decibels, if the peak hold is enabled.
+    pub fn peak_hold_db(&self, channel: usize) -> Option<f32> {
+        self.peak_hold?;
+        self.channels.get(channel).map(|channel| channel.hold_db)
+    }
+
+    /// Whether a channel has clipped since the clip indicators were last reset.
+    pub fn is_clipped(&self, channel: usize) -> bool {
+        self.channels
+            .get(channel)
+            .is_some_and(|channel| channel.clipped)
+    }
+
+    pub fn reset_clip(&mut self) {
+        for channel in &mut self.channels {
+            channel.clipped = false;
+        }
+    }
+
+    /// The position of a level along the meter, between 0 and 1.
+    fn fraction(&self, db: f32) -> f32 {
+        let range = self.max_db - self.min_db;
+        if range <= 0. {
+            return 0.;
+        }
+
+        ((db - self.min_db) / range).clamp(0., 1.)
+    }
+
+    /// Apply the ballistics to what the audio thread sent since the last frame, `dt` seconds ago.
+    fn update(&mut self, dt: f32) {
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let (peak, sum_squares, num_samples) = self.source.take(index);
+            if num_samples > 0 {
+                channel.mean_square = sum_squares / num_samples as f32;
+            }
+
+            let peak_db = util::gain_to_db(peak);
+            if peak_db >= self.clip_db {
+                channel.clipped = true;
+            }
+
+            channel.level_db = match self.ballistics {
+                MeterBallistics::Peak { release } => {
+                    if peak_db >= channel.level_db {
+                        peak_db
+                    } else {
+                        (channel.level_db - release * dt).max(peak_db)
+                    }
+                }
+                MeterBallistics::Rms { window } => {
+                    channel.smoothed +=
+                        (channel.mean_square - channel.smoothed) * smoothing(dt, window);
+                    util::gain_to_db(channel.smoothed.sqrt())
+                }
+                MeterBallistics::Vu => {
+                    channel.smoothed += (channel.mean_square.sqrt() - channel.smoothed)
+                        * smoothing(dt, VU_TIME_CONSTANT);
+                    util::gain_to_db(channel.smoothed)
+                }
+            };
+
+            // Once the hold time has passed, the marker follows the level down until it rises again
+            if let Some(peak_hold) = self.peak_hold {
+                if channel.level_db >= channel.hold_db {
+                    channel.hold_db = channel.level_db;
+                    channel.hold_time = 0.;
+                } else if channel.hold_time >= peak_hold {
+                    channel.hold_db = channel.level_db;
+                } else {
+                    channel.hold_time += dt;
+                }
+            }
+        }
+    }
+}
+
+/// A [`LevelMeter`]. The default size fits the meter's orientation, but the node's style can be
+/// changed freely.
+#[derive(Bundle)]
+pub struct LevelMeterBundle {
+    pub node: NodeBundle,
+    pub interaction: Interaction,
+    pub meter: LevelMeter,
+}
+
+impl LevelMeterBundle {
+    pub fn new(meter: LevelMeter) -> Self {
+        let style = match meter.orientation {
+            MeterOrientation::Vertical => Style {
+                flex_direction: FlexDirection::Row,
+                column_gap: Val::Px(GAP),
+                height: Val::Px(DEFAULT_METER_LENGTH),
+                ..default()
+            },
+            MeterOrientation::Horizontal => Style {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(GAP),
+                width: Val::Px(DEFAULT_METER_LENGTH),
+                ..default()
+            },
+        };
+
+        Self {
+            node: NodeBundle { style, ..default() },
+            interaction: Interaction::default(),
+            meter,
+        }
+    }
+}
+
+//<========== Plugin ==========>//
+
+pub struct LevelMeterPlugin;
+
+impl Plugin for LevelMeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_level_meter_nodes,
+                reset_clip_on_click,
+                update_level_meters,
+            )
+                .chain(),
+        );
+    }
+}
+
+//<========== Systems ==========>//
+
+fn spawn_level_meter_nodes(
+    mut commands: Commands,
+    mut q_meter: Query<(Entity, &mut LevelMeter), Added<LevelMeter>>,
+) {
+    for (entity, mut meter) in &mut q_meter {
+        let vertical = meter.orientation == MeterOrientation::Vertical;
+        let colors = meter.colors;
+        let mut nodes = Vec::with_capacity(meter.channels.len());
+
+        commands.entity(entity).with_children(|parent| {
+            for _ in 0..meter.channels.len() {
+                // The clip indicator sits at the top or right end of the bar
+                let track_style = if vertical {
+                    Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        width: Val::Px(DEFAULT_BAR_THICKNESS),
+                        height: Val::Percent(100.),
+                        row_gap: Val::Px(GAP),
+                        ..default()
+                    }
+                } else {
+                    Style {
+                        flex_direction: FlexDirection::Row,
+                        width: Val::Percent(100.),
+                        height: Val::Px(DEFAULT_BAR_THICKNESS),
+                        column_gap: Val::Px(GAP),
+                        ..default()
+                    }
+                };
+                let bar_style = Style {
+                    flex_grow: 1.,
+                    overflow: Overflow::clip(),
+                    ..default()
+                };
+                let (fill_style, hold_style, clip_style) = if vertical {
+                    (
+                        Style {
+                            position_type: PositionType::Absolute,
+                            bottom: Val::Px(0.),
+                            width: Val::Percent(100.),
+                            height: Val::Percent(0.),
+                            ..default()
+                        },
+                        Style {
+                            position_type: PositionType::Absolute,
+                            bottom: Val::Percent(0.),
+                            width: Val::Percent(100.),
+                            height: Val::Px(HOLD_MARKER_SIZE),
+                            // Keeps the marker inside the bar when the level is at the top
+                            margin: UiRect::bottom(Val::Px(-HOLD_MARKER_SIZE)),
+                            display: Display::None,
+                            ..default()
+                        },
+                        Style {
+                            width: Val::Percent(100.),
+                            height: Val::Px(CLIP_INDICATOR_SIZE),
+                            ..default()
+                        },
+                    )
+                } else {
+                    (
+                        Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(0.),
+                            width: Val::Percent(0.),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                        Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Percent(0.),
+                            width: Val::Px(HOLD_MARKER_SIZE),
+                            height: Val::Percent(100.),
+                            margin: UiRect::left(Val::Px(-HOLD_MARKER_SIZE)),
+                            display: Display::None,
+                            ..default()
+                        },
+                        Style {
+                            width: Val::Px(CLIP_INDICATOR_SIZE),
+                            height: Val::Percent(100.),
+                            ..default()
+                        },
+                    )
+                };
+
+                let mut channel_nodes = MeterChannelNodes {
+                    fill: Entity::PLACEHOLDER,
+                    hold: Entity::PLACEHOLDER,
+                    clip: Entity::PLACEHOLDER,
+                };
+                parent
+                    .spawn(NodeBundle {
+                        style: track_style,
+                        ..default()
+                    })
+                    .with_children(|track| {
+                        track
+                            .spawn(NodeBundle {
+                                style: bar_style,
+                                background_color: colors.background.into(),
+                                ..default()
+                            })
+                            .with_children(|bar| {
+                                channel_nodes.fill = bar
+                                    .spawn(NodeBundle {
+                                        style: fill_style,
+                                        background_color: colors.normal.into(),
+                                        ..default()
+                                    })
+                                    .id();
+                                channel_nodes.hold = bar
+                                    .spawn(NodeBundle {
+                                        style: hold_style,
+                                        background_color: colors.peak_hold.into(),
+                                        ..default()
+                                    })
+                                    .id();
+                            });
+                        channel_nodes.clip = track
+                            .spawn(NodeBundle {
+                                style: clip_style,
+                                background_color: colors.clip_idle.into(),
+                                ..default()
+                            })
+                            .id();
+                    });
+                nodes.push(channel_nodes);
+            }
+
+            let Some(step) = meter.scale_step else {
+                return;
+            };
+
+            // The scale leaves room for the clip indicators so its labels line up with the bars
+            let scale_style = if vertical {
+                Style {
+                    width: Val::Px(SCALE_SIZE),
+                    margin: UiRect::top(Val::Px(CLIP_INDICATOR_SIZE + GAP)),
+                    ..default()
+                }
+            } else {
+                Style {
+                    height: Val::Px(SCALE_SIZE / 2.),
+                    margin: UiRect::right(Val::Px(CLIP_INDICATOR_SIZE + GAP)),
+                    ..default()
+                }
+            };
+            parent
+                .spawn(NodeBundle {
+                    style: scale_style,
+                    ..default()
+                })
+                .with_children(|scale| {
+                    let mut db = meter.max_db;
+                    while db >= meter.min_db {
+                        let position = Val::Percent(meter.fraction(db) * 100.);
+                        let style = if vertical {
+                            Style {
+                                position_type: PositionType::Absolute,
+                                bottom: position,
+                                left: Val::Px(0.),
+                                ..default()
+                            }
+                        } else {
+                            Style {
+                                position_type: PositionType::Absolute,
+                                left: position,
+                                top: Val::Px(0.),
+                                ..default()
+                            }
+                        };
+
+                        scale.spawn(
+                            TextBundle::from_section(
+                                format!("{db:.0}"),
+                                TextStyle {
+                                    font_size: SCALE_FONT_SIZE,
+                                    color: colors.scale,
+                                    ..default()
+                                },
+                            )
+                            .with_style(style),
+                        );
+                        db -= step;
+                    }
+                });
+        });
+
+        meter.nodes = nodes;
+    }
+}
+
+fn reset_clip_on_click(
+    mut q_meter: Query<(&Interaction, &mut LevelMeter), Changed<Interaction>>,
+) {
+    for (interaction, mut meter) in &mut q_meter {
+        if *interaction == Interaction::Pressed {
+            meter.reset_clip();
+        }
+    }
+}
+
+fn update_level_meters(
+    time: Res<Time>,
+    mut q_meter: Query<&mut LevelMeter>,
+    mut q_style: Query<&mut Style>,
+    mut q_color: Query<&mut BackgroundColor>,
+) {
+    let dt = time.delta_seconds();
+
+    for mut meter in &mut q_meter {
+        meter.update(dt);
+
+        // The styles are only touched when something moved, so idle meters don't cause redraws
+        let vertical = meter.orientation == MeterOrientation::Vertical;
+        for (channel, nodes) in meter.channels.iter().zip(&meter.nodes) {
+            let level = Val::Percent(meter.fraction(channel.level_db) * 100.);
+            if let Ok(mut style) = q_style.get_mut(nodes.fill) {
+                if vertical && style.height != level {
+                    style.height = level;
+                } else if !vertical && style.width != level {
+                    style.width = level;
+                }
+            }
+
+            let fill_color = if channel.level_db >= meter.warning_db {
+                meter.colors.warning
+            } else {
+                meter.colors.normal
+            };
+            if let Ok(mut color) = q_color.get_mut(nodes.fill) {
+                color.set_if_neq(fill_color.into());
+            }
+
+            let hold_fraction = meter.fraction(channel.hold_db);
+            if let Ok(mut style) = q_style.get_mut(nodes.hold) {
+                let display = if meter.peak_hold.is_some() && hold_fraction > 0. {
+                    Display::Flex
+                } else {
+                    Display::None
+                };
+                if style.display != display {
+                    style.display = display;
+                }
+
+                let position = Val::Percent(hold_fraction * 100.);
+                if vertical && style.bottom != position {
+                    style.bottom = position;
+                } else if !vertical && style.left != position {
+                    style.left = position;
+                }
+            }
+
+            let clip_color = if channel.clipped {
+                meter.colors.clip
+            } else {
+                meter.colors.clip_idle
+            };
+            if let Ok(mut color) = q_color.get_mut(nodes.clip) {
+                color.set_if_neq(clip_color.into());
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+mod canvas;
+pub mod level_meter;
+pub mod oscilloscope;
+pub mod resize_handle;
+pub mod spectrum_analyzer;
+
+/// The weight of the new value in a one-pole smoother with the given time constant in seconds,
+/// after `dt` seconds.
+fn smoothing(dt: f32, time_constant: f32) -> f32 {
+    if time_constant <= 0. {
+        return 1.;
+    }
+
+    1. - (-dt / time_constant).exp()
+}
@@ -0,0 +1,432 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::{PrimaryWindow, RequestRedraw},
+};
+use nih_plug::{buffer::Buffer, prelude::AtomicF32};
+
+use super::canvas::{canvas_size, new_canvas_image, Canvas};
+use crate::redraw_plugin::should_update;
+
+/// The default width and height of the scope in logical pixels.
+const DEFAULT_SCOPE_SIZE: (f32, f32) = (320., 160.);
+/// The shortest time window the scope can be zoomed in to, in samples.
+const MIN_WINDOW_SAMPLES: usize = 16;
+/// How much the time window changes per line scrolled.
+const ZOOM_STEP: f32 = 1.1;
+/// The number of vertical grid lines, including the ones at the edges.
+const GRID_DIVISIONS: u32 = 4;
+
+//<========== Audio thread ==========>//
+
+#[derive(Debug)]
+struct ScopeBuffer {
+    num_channels: usize,
+    capacity: usize,
+    /// The channels' samples, one `capacity` sized ring after the other.
+    samples: Box<[AtomicF32]>,
+    /// The total number of samples written per channel. This wraps around.
+    write_pos: AtomicUsize,
+    sample_rate: AtomicF32,
+}
+
+/// The last couple of samples of one or more channels, shared between the audio thread and one or
+/// more [`Oscilloscope`]s or [`SpectrumAnalyzer`][super::spectrum_analyzer::SpectrumAnalyzer]s.
+/// The plugin writes to this from `process()` without blocking or allocating, and the widgets read
+/// the latest samples every frame.
+///
+/// Every sample pushed here is copied into the ring buffer, which is wasted work while
+/// [`BevyState::is_open()`][crate::BevyState::is_open()] is false. The widgets only ever show the
+/// latest samples, so nothing is lost by not feeding them in the meantime.
+#[derive(Debug, Clone)]
+pub struct ScopeSource {
+    buffer: Arc<ScopeBuffer>,
+}
+
+impl ScopeSource {
+    /// Keep the last `capacity` samples of every channel. The scope can show at most half of that,
+    /// the other half is used to look for a trigger.
+    pub fn new(num_channels: usize, capacity: usize) -> Self {
+        let capacity = capacity.max(MIN_WINDOW_SAMPLES * 2);
+
+        Self {
+            buffer: Arc::new(ScopeBuffer {
+                num_channels,
+                capacity,
+                samples: (0..num_channels * capacity)
+                    .map(|_| AtomicF32::new(0.))
+                    .collect(),
+                write_pos: AtomicUsize::new(0),
+                sample_rate: AtomicF32::new(44100.),
+            }),
+        }
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.buffer.num_channels
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity
+    }
+
+    /// Needed to convert the scope's time window to samples. Call this from the plugin's
+    /// `initialize()`.
+    pub fn set_sample_rate(&self, sample_rate: f32) {
+        self.buffer
+            .sample_rate
+            .store(sample_rate, Ordering::Relaxed);
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.buffer.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Add a block of samples for every channel. All channels should be the same length. Channels
+    /// the source wasn't created with are ignored.
+    pub fn push_channels<S: AsRef<[f32]>>(&self, channels: &[S]) {
+        let buffer = &*self.buffer;
+        let write_pos = buffer.write_pos.load(Ordering::Relaxed);
+
+        let mut num_samples = 0;
+        for (channel, samples) in channels.iter().take(buffer.num_channels).enumerate() {
+            let samples = samples.as_ref();
+            let ring = &buffer.samples[channel * buffer.capacity..(channel + 1) * buffer.capacity];
+            for (offset, sample) in samples.iter().enumerate() {
+                ring[write_pos.wrapping_add(offset) % buffer.capacity]
+                    .store(*sample, Ordering::Relaxed);
+            }
+            num_samples = num_samples.max(samples.len());
+        }
+
+        buffer
+            .write_pos
+            .store(write_pos.wrapping_add(num_samples), Ordering::Release);
+    }
+
+    /// Add a whole buffer, see [`push_channels()`][Self::push_channels()].
+    pub fn push_buffer(&self, buffer: &Buffer) {
+        self.push_channels(buffer.as_slice_immutable());
+    }
+
+    pub(crate) fn write_pos(&self) -> usize {
+        self.buffer.write_pos.load(Ordering::Acquire)
+    }
+
+    /// Fill `out` with a channel's samples leading up to `end`, a value of
+    /// [`write_pos()`][Self::write_pos()].
+    pub(crate) fn read(&self, channel: usize, end: usize, out: &mut [f32]) {
+        let buffer = &*self.buffer;
+        if channel >= buffer.num_channels {
+            out.fill(0.);
+            return;
+        }
+
+        let ring = &buffer.samples[channel * buffer.capacity..(channel + 1) * buffer.capacity];
+        let start = end.wrapping_sub(out.len());
+        for (offset, sample) in out.iter_mut().enumerate() {
+            *sample = ring[start.wrapping_add(offset) % buffer.capacity].load(Ordering::Relaxed);
+        }
+    }
+}
+
+//<========== Components ==========>//
+
+/// What the [`Oscilloscope`] lines its display up to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ScopeTrigger {
+    /// Always show the latest samples.
+    Free,
+    /// Start the display where the channel's signal last rose through `level`. Falls back to the
+    /// latest samples when there is no such edge.
+    RisingEdge { channel: usize, level: f32 },
+}
+
+impl Default for ScopeTrigger {
+    fn default() -> Self {
+        Self::RisingEdge {
+            channel: 0,
+            level: 0.,
+        }
+    }
+}
+
+/// Shows the latest samples of every channel in a [`ScopeSource`], see [`OscilloscopeBundle`].
+/// Scrolling over the scope zooms its time window in and out. Add [`OscilloscopePlugin`] to the
+/// editor to use this.
+#[derive(Component, Clone, Debug)]
+pub struct Oscilloscope {
+    source: ScopeSource,
+    window: f32,
+    trigger: ScopeTrigger,
+    range: f32,
+    channel_colors: Vec<Color>,
+    background: Color,
+    grid: Color,
+    line_thickness: f32,
+
+    image: Handle<Image>,
+    samples: Vec<f32>,
+    /// The source's write position and the canvas size of the last drawn trace. The scope isn't
+    /// redrawn until one of them changes.
+    drawn: Option<(usize, (u32, u32))>,
+}
+
+impl Oscilloscope {
+    pub fn new(source: ScopeSource) -> Self {
+        Self {
+            source,
+            window: 0.02,
+            trigger: ScopeTrigger::default(),
+            range: 1.,
+            channel_colors: vec![
+                Color::srgb(0.3, 0.85, 0.4),
+                Color::srgb(0.35, 0.6, 0.95),
+                Color::srgb(0.95, 0.6, 0.25),
+                Color::srgb(0.85, 0.4, 0.85),
+            ],
+            background: Color::srgb(0.08, 0.08, 0.08),
+            grid: Color::srgba(1., 1., 1., 0.12),
+            line_thickness: 1.,
+
+            image: Handle::default(),
+            samples: Vec::new(),
+            drawn: None,
+        }
+    }
+
+    /// The time in seconds shown at once. Defaults to 20 ms.
+    pub fn with_window(mut self, window: f32) -> Self {
+        self.set_window(window);
+        self
+    }
+
+    /// Defaults to a rising edge through zero on the first channel.
+    pub fn with_trigger(self, trigger: ScopeTrigger) -> Self {
+        Self { trigger, ..self }
+    }
+
+    /// The amplitude at the top and bottom edges. Defaults to 1.
+    pub fn with_range(self, range: f32) -> Self {
+        Self {
+            range: range.max(f32::EPSILON),
+            ..self
+        }
+    }
+
+    /// The channels' colors. These repeat when there are more channels than colors.
+    pub fn with_channel_colors(self, channel_colors: Vec<Color>) -> Self {
+        Self {
+            channel_colors,
+            ..self
+        }
+    }
+
+    pub fn with_background(self, background: Color) -> Self {
+        Self { background, ..self }
+    }
+
+    pub fn with_grid(self, grid: Color) -> Self {
+        Self { grid, ..self }
+    }
+
+    /// The traces' thickness in logical pixels. Defaults to 1.
+    pub fn with_line_thickness(self, line_thickness: f32) -> Self {
+        Self {
+            line_thickness,
+            ..self
+        }
+    }
+
+    pub fn window(&self) -> f32 {
+        self.window
+    }
+
+    /// Change the time window. This is limited by the [`ScopeSource`]'s capacity.
+    pub fn set_window(&mut self, window: f32) {
+        let sample_rate = self.source.sample_rate();
+        let min = MIN_WINDOW_SAMPLES as f32 / sample_rate;
+        let max = (self.source.capacity() / 2) as f32 / sample_rate;
+        self.window = window.clamp(min, max);
+        self.drawn = None;
+    }
+
+    pub fn trigger(&self) -> ScopeTrigger {
+        self.trigger
+    }
+
+    pub fn set_trigger(&mut self, trigger: ScopeTrigger) {
+        self.trigger = trigger;
+        self.drawn = None;
+    }
+
+    /// The window's length in samples.
+    fn window_samples(&self) -> usize {
+        ((self.window * self.source.sample_rate()).round() as usize)
+            .clamp(MIN_WINDOW_SAMPLES, self.source.capacity() / 2)
+    }
+
+    /// Where the window starts within the last `2 * window_samples` samples.
+    fn trigger_offset(&mut self, end: usize, window_samples: usize) -> usize {
+        let ScopeTrigger::RisingEdge { channel, level } = self.trigger else {
+            return window_samples;
+        };
+
+        // Take the most recent edge that still leaves room for a full window
+        self.samples.resize(window_samples * 2, 0.);
+        self.source.read(channel, end, &mut self.samples);
+        (1..=window_samples)
+            .rev()
+            .find(|&i| self.samples[i - 1] < level && self.samples[i] >= level)
+            .unwrap_or(window_samples)
+    }
+
+    fn draw(&mut self, canvas: &mut Canvas, end: usize, scale_factor: f32) {
+        canvas.clear(self.background);
+
+        let (width, height) = (canvas.width() as f32, canvas.height() as f32);
+        canvas.horizontal_line(height / 2., self.grid);
+        for division in 1..GRID_DIVISIONS {
+            canvas.vertical_line(width * division as f32 / GRID_DIVISIONS as f32, self.grid);
+        }
+
+        let window_samples = self.window_samples();
+        let offset = self.trigger_offset(end, window_samples);
+        let window_end = end.wrapping_sub(window_samples - offset);
+        let x_step = (width - 1.) / (window_samples - 1) as f32;
+        self.samples.resize(window_samples, 0.);
+
+        for channel in 0..self.source.num_channels() {
+            self.source.read(channel, window_end, &mut self.samples);
+            let color = self
+                .channel_colors
+                .get(channel % self.channel_colors.len().max(1))
+                .copied()
+                .unwrap_or(Color::WHITE);
+
+            let range = self.range;
+            canvas.polyline(
+                self.samples.iter().enumerate().map(|(i, sample)| {
+                    let y = (0.5 - sample / range / 2.).clamp(0., 1.);
+                    Vec2::new(i as f32 * x_step, y * (height - 1.))
+                }),
+                self.line_thickness * scale_factor,
+                color,
+            );
+        }
+    }
+}
+
+/// An [`Oscilloscope`], drawn into the node's image.
+#[derive(Bundle)]
+pub struct OscilloscopeBundle {
+    pub image: ImageBundle,
+    pub interaction: Interaction,
+    pub scope: Oscilloscope,
+}
+
+impl OscilloscopeBundle {
+    pub fn new(scope: Oscilloscope) -> Self {
+        Self {
+            image: ImageBundle {
+                style: Style {
+                    width: Val::Px(DEFAULT_SCOPE_SIZE.0),
+                    height: Val::Px(DEFAULT_SCOPE_SIZE.1),
+                    ..default()
+                },
+                ..default()
+            },
+            interaction: Interaction::default(),
+            scope,
+        }
+    }
+}
+
+//<========== Plugin ==========>//
+
+pub struct OscilloscopePlugin;
+
+impl Plugin for OscilloscopePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                init_oscilloscopes,
+                zoom_oscilloscopes,
+                // Drawing a frame the FPS cap drops would be wasted work
+                draw_oscilloscopes.run_if(should_update),
+            )
+                .chain(),
+        );
+    }
+}
+
+//<========== Systems ==========>//
+
+fn init_oscilloscopes(
+    mut images: ResMut<Assets<Image>>,
+    mut q_scope: Query<(&mut Oscilloscope, &mut UiImage), Added<Oscilloscope>>,
+) {
+    for (mut scope, mut ui_image) in &mut q_scope {
+        scope.image = new_canvas_image(&mut images);
+        ui_image.texture = scope.image.clone();
+    }
+}
+
+fn zoom_oscilloscopes(
+    mut wheel_events: EventReader<MouseWheel>,
+    mut q_scope: Query<(&Interaction, &mut Oscilloscope)>,
+) {
+    let lines: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 20.,
+        })
+        .sum();
+    if lines == 0. {
+        return;
+    }
+
+    // Scrolling up zooms in
+    for (interaction, mut scope) in &mut q_scope {
+        if *interaction == Interaction::Hovered {
+            let window = scope.window * ZOOM_STEP.powf(-lines);
+            scope.set_window(window);
+        }
+    }
+}
+
+fn draw_oscilloscopes(
+    mut images: ResMut<Assets<Image>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_scope: Query<(&Node, &mut Oscilloscope)>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let scale_factor = q_window
+        .get_single()
+        .map(|window| window.scale_factor())
+        .unwrap_or(1.);
+
+    for (node, mut scope) in &mut q_scope {
+        let end = scope.source.write_pos();
+        let size = canvas_size(node, scale_factor);
+        if scope.drawn == Some((end, size)) {
+            continue;
+        }
+
+        let Some(image) = images.get_mut(&scope.image) else {
+            continue;
+        };
+
+        let mut canvas = Canvas::new(image, size);
+        scope.draw(&mut canvas, end, scale_factor);
+        scope.drawn = Some((end, size));
+        redraw_events.send(RequestRedraw);
+    }
+}
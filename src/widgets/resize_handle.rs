@@ -0,0 +1,177 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::window_plugin::{NIHBevyState, ResizeRequest};
+
+/// The default width and height of the handle in logical pixels.
+const DEFAULT_HANDLE_SIZE: f32 = 16.;
+
+//<========== Components ==========>//
+
+#[derive(Copy, Clone, Debug)]
+struct ResizeDrag {
+    start_cursor: Vec2,
+    start_size: (u32, u32),
+}
+
+/// A handle that resizes the editor while it's being dragged, usually placed in the bottom right
+/// corner of the window. See [`ResizeHandleBundle`]. The handle's own bounds are applied on top of
+/// the constraints in the [`BevyState`][crate::BevyState]. Add [`ResizeHandlePlugin`] to the editor
+/// to use this.
+#[derive(Component, Clone, Debug)]
+pub struct ResizeHandle {
+    min_size: (u32, u32),
+    max_size: Option<(u32, u32)>,
+    drag: Option<ResizeDrag>,
+}
+
+impl Default for ResizeHandle {
+    fn default() -> Self {
+        Self {
+            min_size: (1, 1),
+            max_size: None,
+            drag: None,
+        }
+    }
+}
+
+impl ResizeHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The smallest size in logical pixels the handle will resize the editor to.
+    pub fn with_min_size(self, width: u32, height: u32) -> Self {
+        Self {
+            min_size: (width.max(1), height.max(1)),
+            ..self
+        }
+    }
+
+    /// The largest size in logical pixels the handle will resize the editor to.
+    pub fn with_max_size(self, width: u32, height: u32) -> Self {
+        Self {
+            max_size: Some((width, height)),
+            ..self
+        }
+    }
+
+    pub fn min_size(&self) -> (u32, u32) {
+        self.min_size
+    }
+
+    pub fn max_size(&self) -> Option<(u32, u32)> {
+        self.max_size
+    }
+
+    /// Whether the handle is currently being dragged.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn clamp(&self, width: u32, height: u32) -> (u32, u32) {
+        let (max_width, max_height) = self.max_size.unwrap_or((u32::MAX, u32::MAX));
+        (
+            width.clamp(self.min_size.0, max_width.max(self.min_size.0)),
+            height.clamp(self.min_size.1, max_height.max(self.min_size.1)),
+        )
+    }
+}
+
+/// A [`ResizeHandle`] pinned to the bottom right corner of its parent node, or of the window when
+/// spawned as a root node.
+#[derive(Bundle)]
+pub struct ResizeHandleBundle {
+    pub node: NodeBundle,
+    pub interaction: Interaction,
+    pub handle: ResizeHandle,
+}
+
+impl ResizeHandleBundle {
+    pub fn new(handle: ResizeHandle) -> Self {
+        Self {
+            handle,
+            ..default()
+        }
+    }
+}
+
+impl Default for ResizeHandleBundle {
+    fn default() -> Self {
+        Self {
+            node: NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                    width: Val::Px(DEFAULT_HANDLE_SIZE),
+                    height: Val::Px(DEFAULT_HANDLE_SIZE),
+                    ..default()
+                },
+                background_color: Color::srgba(1., 1., 1., 0.25).into(),
+                z_index: ZIndex::Global(i32::MAX),
+                ..default()
+            },
+            interaction: Interaction::default(),
+            handle: ResizeHandle::default(),
+        }
+    }
+}
+
+//<========== Plugin ==========>//
+
+pub struct ResizeHandlePlugin;
+
+impl Plugin for ResizeHandlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_resize_handle);
+    }
+}
+
+//<========== Systems ==========>//
+
+fn update_resize_handle(
+    mouse: Res<ButtonInput<MouseButton>>,
+    bevy_state: Res<NIHBevyState>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_handle: Query<(&Interaction, &mut ResizeHandle)>,
+    mut resize_requests: EventWriter<ResizeRequest>,
+) {
+    let Ok(window) = q_window.get_single() else {
+        return;
+    };
+
+    for (interaction, mut handle) in &mut q_handle {
+        // The button may have been released outside of the window
+        if !mouse.pressed(MouseButton::Left) {
+            if handle.drag.is_some() {
+                handle.drag = None;
+            }
+            continue;
+        }
+
+        let Some(cursor) = window.cursor_position() else {
+            continue;
+        };
+
+        let Some(drag) = handle.drag else {
+            if *interaction == Interaction::Pressed {
+                handle.drag = Some(ResizeDrag {
+                    start_cursor: cursor,
+                    start_size: bevy_state.state().size(),
+                });
+            }
+            continue;
+        };
+
+        // The window's top left corner stays in place, so the cursor's offset is the size change.
+        // That offset is zoomed while the editor's size isn't.
+        let diff = (cursor - drag.start_cursor) / bevy_state.state().zoom();
+        let (width, height) = bevy_state.state().clamp_size(handle.clamp(
+            (drag.start_size.0 as f32 + diff.x).round().max(0.) as u32,
+            (drag.start_size.1 as f32 + diff.y).round().max(0.) as u32,
+        ));
+        if (width, height) != bevy_state.state().size() {
+            resize_requests.send(ResizeRequest::new(width, height));
+        }
+    }
+}
@@ -0,0 +1,487 @@
+use std::{fmt, sync::Arc};
+
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, RequestRedraw},
+};
+use nih_plug::util;
+use realfft::{num_complex::Complex, RealFftPlanner, RealToComplex};
+
+use super::{
+    canvas::{canvas_size, new_canvas_image, Canvas},
+    oscilloscope::ScopeSource,
+    smoothing,
+};
+use crate::redraw_plugin::should_update;
+
+/// The default width and height of the analyzer in logical pixels.
+const DEFAULT_ANALYZER_SIZE: (f32, f32) = (320., 160.);
+/// The frequencies that get a grid line and a label, if they're within the analyzer's range.
+const GRID_FREQUENCIES: [f32; 10] = [
+    20., 50., 100., 200., 500., 1000., 2000., 5000., 10000., 20000.,
+];
+/// The distance between the horizontal grid lines in decibels.
+const GRID_DB_STEP: f32 = 12.;
+const LABEL_FONT_SIZE: f32 = 9.;
+/// The frequency the slope pivots around.
+const SLOPE_PIVOT: f32 = 1000.;
+
+//<========== Components ==========>//
+
+/// The FFT and the buffers it works in, set up on the analyzer's first frame.
+struct SpectrumFft {
+    plan: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Scales the bins' magnitudes so a full scale sine wave reads as 0 dB.
+    gain: f32,
+    input: Vec<f32>,
+    channel: Vec<f32>,
+    output: Vec<Complex<f32>>,
+}
+
+impl SpectrumFft {
+    fn new(fft_size: usize) -> Self {
+        let plan = RealFftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        // A Hann window
+        let window: Vec<f32> = (0..fft_size)
+            .map(|i| {
+                0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / fft_size as f32).cos()
+            })
+            .collect();
+        let gain = 2. / window.iter().sum::<f32>();
+
+        Self {
+            input: plan.make_input_vec(),
+            channel: plan.make_input_vec(),
+            output: plan.make_output_vec(),
+            plan,
+            window,
+            gain,
+        }
+    }
+}
+
+impl fmt::Debug for SpectrumFft {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpectrumFft")
+            .field("fft_size", &self.window.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Clone for SpectrumFft {
+    fn clone(&self) -> Self {
+        Self::new(self.window.len())
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct AnalyzerColors {
+    pub background: Color,
+    pub line: Color,
+    /// The area below the line.
+    pub fill: Color,
+    pub grid: Color,
+    pub labels: Color,
+}
+
+impl Default for AnalyzerColors {
+    fn default() -> Self {
+        Self {
+            background: Color::srgb(0.08, 0.08, 0.08),
+            line: Color::srgb(0.35, 0.75, 0.95),
+            fill: Color::srgba(0.35, 0.75, 0.95, 0.2),
+            grid: Color::srgba(1., 1., 1., 0.12),
+            labels: Color::srgba(1., 1., 1., 0.6),
+        }
+    }
+}
+
+/// Shows the spectrum of the latest samples in a [`ScopeSource`], so one source can feed both an
+/// [`Oscilloscope`][super::oscilloscope::Oscilloscope] and an analyzer. The windowing and the FFT
+/// happen on the GUI thread. See [`SpectrumAnalyzerBundle`], and add [`SpectrumAnalyzerPlugin`] to
+/// the editor to use this.
+#[derive(Component, Clone, Debug)]
+pub struct SpectrumAnalyzer {
+    source: ScopeSource,
+    channel: Option<usize>,
+    fft_size: usize,
+    min_frequency: f32,
+    max_frequency: f32,
+    min_db: f32,
+    max_db: f32,
+    averaging: f32,
+    slope: f32,
+    colors: AnalyzerColors,
+    line_thickness: f32,
+
+    image: Handle<Image>,
+    fft: Option<SpectrumFft>,
+    /// The averaged level of every bin in decibels.
+    levels: Vec<f32>,
+    /// When the spectrum was last analyzed, in seconds since the app started.
+    last_analysis: Option<f32>,
+    /// Where the source's write position was and how large the canvas was when the spectrum was
+    /// last drawn. New samples trigger a new analysis, while a resize only redraws the spectrum.
+    drawn: Option<(usize, (u32, u32))>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(source: ScopeSource) -> Self {
+        Self {
+            fft_size: 4096.min(largest_power_of_two(source.capacity())),
+            source,
+            channel: None,
+            min_frequency: 20.,
+            max_frequency: 20000.,
+            min_db: -84.,
+            max_db: 6.,
+            averaging: 0.1,
+            slope: 0.,
+            colors: AnalyzerColors::default(),
+            line_thickness: 1.,
+
+            image: Handle::default(),
+            fft: None,
+            levels: Vec::new(),
+            last_analysis: None,
+            drawn: None,
+        }
+    }
+
+    /// Analyze a single channel, or `None` to analyze the average of all channels. Defaults to
+    /// `None`.
+    pub fn with_channel(self, channel: Option<usize>) -> Self {
+        Self { channel, ..self }
+    }
+
+    /// The number of samples per FFT. This is rounded down to a power of two, and limited by the
+    /// [`ScopeSource`]'s capacity. Defaults to 4096.
+    pub fn with_fft_size(self, fft_size: usize) -> Self {
+        Self {
+            fft_size: largest_power_of_two(fft_size.min(self.source.capacity())),
+            ..self
+        }
+    }
+
+    /// The frequencies at the left and right edges, spaced logarithmically. Defaults to 20 Hz and
+    /// 20 kHz.
+    pub fn with_frequency_range(self, min_frequency: f32, max_frequency: f32) -> Self {
+        let min_frequency = min_frequency.max(1.);
+        Self {
+            min_frequency,
+            max_frequency: max_frequency.max(min_frequency * 2.),
+            ..self
+        }
+    }
+
+    /// The levels in decibels at the bottom and top edges. Defaults to -84 dB and +6 dB.
+    pub fn with_db_range(self, min_db: f32, max_db: f32) -> Self {
+        Self {
+            min_db: min_db.min(max_db),
+            max_db: max_db.max(min_db),
+            ..self
+        }
+    }
+
+    /// See [`set_averaging()`][Self::set_averaging()]. Defaults to 0.1 seconds.
+    pub fn with_averaging(mut self, averaging: f32) -> Self {
+        self.set_averaging(averaging);
+        self
+    }
+
+    /// See [`set_slope()`][Self::set_slope()]. Defaults to 0 dB per octave.
+    pub fn with_slope(mut self, slope: f32) -> Self {
+        self.set_slope(slope);
+        self
+    }
+
+    pub fn with_colors(self, colors: AnalyzerColors) -> Self {
+        Self { colors, ..self }
+    }
+
+    /// The line's thickness in logical pixels. Defaults to 1.
+    pub fn with_line_thickness(self, line_thickness: f32) -> Self {
+        Self {
+            line_thickness,
+            ..self
+        }
+    }
+
+    pub fn averaging(&self) -> f32 {
+        self.averaging
+    }
+
+    /// The time constant in seconds the bins' levels are smoothed with. 0 shows every analysis as
+    /// is.
+    pub fn set_averaging(&mut self, averaging: f32) {
+        self.averaging = averaging.max(0.);
+    }
+
+    pub fn slope(&self) -> f32 {
+        self.slope
+    }
+
+    /// Tilt the spectrum by this many decibels per octave around 1 kHz. A slope of 3 dB to 4.5 dB
+    /// makes pink noise and most mixes look roughly flat.
+    pub fn set_slope(&mut self, slope: f32) {
+        self.slope = slope;
+        self.levels.clear();
+        self.drawn = None;
+    }
+
+    /// Run the FFT over the samples leading up to `end`, and fold the result into the levels.
+    fn analyze(&mut self, end: usize, dt: f32) {
+        let fft = self
+            .fft
+            .get_or_insert_with(|| SpectrumFft::new(self.fft_size));
+
+        match self.channel {
+            Some(channel) => self.source.read(channel, end, &mut fft.input),
+            None => {
+                fft.input.fill(0.);
+                let num_channels = self.source.num_channels().max(1);
+                for channel in 0..num_channels {
+                    self.source.read(channel, end, &mut fft.channel);
+                    for (input, sample) in fft.input.iter_mut().zip(&fft.channel) {
+                        *input += sample / num_channels as f32;
+                    }
+                }
+            }
+        }
+        for (input, window) in fft.input.iter_mut().zip(&fft.window) {
+            *input *= window;
+        }
+        if fft.plan.process(&mut fft.input, &mut fft.output).is_err() {
+            return;
+        }
+
+        // Starting over shows the first analysis as is instead of fading in from silence
+        let weight = if self.levels.len() == fft.output.len() {
+            smoothing(dt, self.averaging)
+        } else {
+            self.levels = vec![util::MINUS_INFINITY_DB; fft.output.len()];
+            1.
+        };
+
+        let bin_width = self.source.sample_rate() / self.fft_size as f32;
+        for (bin, (level, value)) in self.levels.iter_mut().zip(&fft.output).enumerate().skip(1) {
+            let frequency = bin as f32 * bin_width;
+            let db = util::gain_to_db(value.norm() * fft.gain)
+                + self.slope * (frequency / SLOPE_PIVOT).log2();
+            *level += (db - *level) * weight;
+        }
+    }
+
+    /// The horizontal position of a frequency, between 0 and 1.
+    fn frequency_fraction(&self, frequency: f32) -> f32 {
+        (frequency / self.min_frequency).ln() / (self.max_frequency / self.min_frequency).ln()
+    }
+
+    /// The vertical position of a level, between 0 at the top and 1 at the bottom.
+    fn db_fraction(&self, db: f32) -> f32 {
+        1. - (db - self.min_db) / (self.max_db - self.min_db).max(f32::EPSILON)
+    }
+
+    /// The level shown in column `x` out of `width`.
+    fn column_level(&self, x: f32, width: f32) -> f32 {
+        let bins_per_hz = self.fft_size as f32 / self.source.sample_rate();
+        let ratio = self.max_frequency / self.min_frequency;
+        let bin_at = |x: f32| self.min_frequency * ratio.powf(x / width) * bins_per_hz;
+        let last_bin = self.levels.len() - 1;
+
+        // At the top end a column covers multiple bins, so it shows the loudest of them. At the
+        // bottom end the bins are interpolated.
+        let (start, end) = (bin_at(x - 0.5), bin_at(x + 0.5));
+        if end - start >= 2. {
+            let start = (start.ceil() as usize).clamp(1, last_bin);
+            let end = (end.floor() as usize).clamp(start, last_bin);
+            return self.levels[start..=end]
+                .iter()
+                .copied()
+                .fold(util::MINUS_INFINITY_DB, f32::max);
+        }
+
+        let bin = bin_at(x).clamp(1., last_bin as f32);
+        let (low, high) = (bin.floor() as usize, bin.ceil() as usize);
+        let t = bin - low as f32;
+        self.levels[low] * (1. - t) + self.levels[high] * t
+    }
+
+    fn draw(&self, canvas: &mut Canvas, scale_factor: f32) {
+        canvas.clear(self.colors.background);
+
+        let (width, height) = (canvas.width() as f32, canvas.height() as f32);
+        for frequency in GRID_FREQUENCIES {
+            let fraction = self.frequency_fraction(frequency);
+            if fraction > 0. && fraction < 1. {
+                canvas.vertical_line(fraction * width, self.colors.grid);
+            }
+        }
+        for db in self.grid_levels() {
+            canvas.horizontal_line(self.db_fraction(db) * height, self.colors.grid);
+        }
+
+        if self.levels.len() < 2 {
+            return;
+        }
+
+        let points: Vec<Vec2> = (0..canvas.width())
+            .map(|x| {
+                let y = self.db_fraction(self.column_level(x as f32, width)).clamp(0., 1.);
+                Vec2::new(x as f32, y * (height - 1.))
+            })
+            .collect();
+        for point in &points {
+            canvas.fill_below(point.x as i32, point.y, self.colors.fill);
+        }
+        canvas.polyline(points, self.line_thickness * scale_factor, self.colors.line);
+    }
+
+    /// The levels that get a grid line and a label, from the top down.
+    fn grid_levels(&self) -> impl Iterator<Item = f32> + '_ {
+        let top = (self.max_db / GRID_DB_STEP).floor() * GRID_DB_STEP;
+        (0..)
+            .map(move |i| top - i as f32 * GRID_DB_STEP)
+            .take_while(|db| *db > self.min_db)
+    }
+}
+
+fn largest_power_of_two(n: usize) -> usize {
+    match n {
+        0 | 1 => 2,
+        n => 1 << (usize::BITS - 1 - n.leading_zeros()),
+    }
+}
+
+/// A [`SpectrumAnalyzer`], drawn into the node's image.
+#[derive(Bundle)]
+pub struct SpectrumAnalyzerBundle {
+    pub image: ImageBundle,
+    pub analyzer: SpectrumAnalyzer,
+}
+
+impl SpectrumAnalyzerBundle {
+    pub fn new(analyzer: SpectrumAnalyzer) -> Self {
+        Self {
+            image: ImageBundle {
+                style: Style {
+                    width: Val::Px(DEFAULT_ANALYZER_SIZE.0),
+                    height: Val::Px(DEFAULT_ANALYZER_SIZE.1),
+                    ..default()
+                },
+                ..default()
+            },
+            analyzer,
+        }
+    }
+}
+
+//<========== Plugin ==========>//
+
+pub struct SpectrumAnalyzerPlugin;
+
+impl Plugin for SpectrumAnalyzerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                init_spectrum_analyzers,
+                draw_spectrum_analyzers.run_if(should_update),
+            )
+                .chain(),
+        );
+    }
+}
+
+//<========== Systems ==========>//
+
+fn init_spectrum_analyzers(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut q_analyzer: Query<(Entity, &mut SpectrumAnalyzer, &mut UiImage), Added<SpectrumAnalyzer>>,
+) {
+    for (entity, mut analyzer, mut ui_image) in &mut q_analyzer {
+        analyzer.image = new_canvas_image(&mut images);
+        ui_image.texture = analyzer.image.clone();
+
+        let text_style = TextStyle {
+            font_size: LABEL_FONT_SIZE,
+            color: analyzer.colors.labels,
+            ..default()
+        };
+        commands.entity(entity).with_children(|parent| {
+            for frequency in GRID_FREQUENCIES {
+                let fraction = analyzer.frequency_fraction(frequency);
+                if !(0. ..1.).contains(&fraction) {
+                    continue;
+                }
+
+                let label = if frequency >= 1000. {
+                    format!("{}k", frequency / 1000.)
+                } else {
+                    format!("{frequency}")
+                };
+                parent.spawn(
+                    TextBundle::from_section(label, text_style.clone()).with_style(Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(fraction * 100.),
+                        bottom: Val::Px(0.),
+                        ..default()
+                    }),
+                );
+            }
+
+            for db in analyzer.grid_levels() {
+                parent.spawn(
+                    TextBundle::from_section(format!("{db:.0}"), text_style.clone()).with_style(
+                        Style {
+                            position_type: PositionType::Absolute,
+                            top: Val::Percent(analyzer.db_fraction(db) * 100.),
+                            right: Val::Px(2.),
+                            ..default()
+                        },
+                    ),
+                );
+            }
+        });
+    }
+}
+
+fn draw_spectrum_analyzers(
+    time: Res<Time>,
+    mut images: ResMut<Assets<Image>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_analyzer: Query<(&Node, &mut SpectrumAnalyzer)>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let scale_factor = q_window
+        .get_single()
+        .map(|window| window.scale_factor())
+        .unwrap_or(1.);
+    let now = time.elapsed_seconds();
+
+    for (node, mut analyzer) in &mut q_analyzer {
+        let end = analyzer.source.write_pos();
+        let size = canvas_size(node, scale_factor);
+        let drawn_end = analyzer.drawn.map(|(end, _)| end);
+        if analyzer.drawn == Some((end, size)) {
+            continue;
+        }
+
+        if drawn_end != Some(end) {
+            let dt = analyzer.last_analysis.map_or(0., |last| now - last);
+            analyzer.analyze(end, dt);
+            analyzer.last_analysis = Some(now);
+        }
+
+        let Some(image) = images.get_mut(&analyzer.image) else {
+            continue;
+        };
+
+        let mut canvas = Canvas::new(image, size);
+        analyzer.draw(&mut canvas, scale_factor);
+        analyzer.drawn = Some((end, size));
+        redraw_events.send(RequestRedraw);
+    }
+}
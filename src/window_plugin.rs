@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use crossbeam::atomic::AtomicCell;
+
+use crate::{param_plugin::NIHContext, BevyState};
+
+//<========== Resources ==========>//
+
+/// The editor's [`BevyState`], available while the editor is open.
+#[derive(Resource)]
+pub struct NIHBevyState(Arc<BevyState>);
+
+impl NIHBevyState {
+    pub(crate) fn new(state: Arc<BevyState>) -> Self {
+        Self(state)
+    }
+
+    pub fn state(&self) -> &BevyState {
+        &self.0
+    }
+}
+
+/// Holds a scale factor the host has set while the editor was open, until it has been applied.
+#[derive(Resource)]
+pub(crate) struct NIHScaleFactor(Arc<AtomicCell<Option<f32>>>);
+
+impl NIHScaleFactor {
+    pub(crate) fn new(pending: Arc<AtomicCell<Option<f32>>>) -> Self {
+        Self(pending)
+    }
+}
+
+//<========== Plugin ==========>//
+
+pub struct NIHWindowPlugin;
+
+impl Plugin for NIHWindowPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ResizeRequest>()
+            .add_event::<ResizeRejected>()
+            .add_event::<ZoomRequest>()
+            .add_event::<ZoomRejected>()
+            .add_event::<ScaleFactorChanged>()
+            .add_event::<ScaleFactorRejected>()
+            .add_systems(Startup, ui_scale_system)
+            .add_systems(PreUpdate, scale_factor_system)
+            .add_systems(PostUpdate, (resize_system, zoom_system).chain());
+    }
+}
+
+//<========== Events ==========>//
+
+/// Ask the host to resize the editor to a new size in logical pixels, before the user's zoom
+/// factor is applied. The size is first constrained by [`BevyState::clamp_size()`]. If the host
+/// accepts the new size it is stored in the [`BevyState`], and thus persisted with the plugin's
+/// state. Otherwise a [`ResizeRejected`] event is sent. Only the last request sent during a frame is
+/// considered.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeRequest {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ResizeRequest {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+}
+
+/// Sent when the host refused a [`ResizeRequest`]. The editor keeps its previous size.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeRejected {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Change the user's zoom factor, e.g. `1.5` for a 150% GUI size. This is combined with the host's
+/// DPI scaling, resizes the window through the host, and scales the Bevy UI through [`UiScale`].
+/// The zoom factor is persisted in the [`BevyState`]. Only the last request sent during a frame is
+/// considered.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ZoomRequest {
+    pub zoom: f32,
+}
+
+impl ZoomRequest {
+    pub fn new(zoom: f32) -> Self {
+        Self { zoom }
+    }
+}
+
+/// Sent when the host refused the window size resulting from a [`ZoomRequest`]. The editor keeps its
+/// previous zoom factor.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ZoomRejected {
+    pub zoom: f32,
+}
+
+/// Sent after the host has changed the editor's DPI scale factor while the editor was open, for
+/// instance because the window was moved to a monitor with a different pixel density.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ScaleFactorChanged {
+    pub scale_factor: f32,
+}
+
+/// Sent when the host refused the window size resulting from a new DPI scale factor. The editor
+/// keeps its previous scale factor.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct ScaleFactorRejected {
+    pub scale_factor: f32,
+}
+
+//<========== Systems ==========>//
+
+fn ui_scale_system(bevy_state: Res<NIHBevyState>, ui_scale: Option<ResMut<UiScale>>) {
+    if let Some(mut ui_scale) = ui_scale {
+        ui_scale.0 = bevy_state.0.zoom();
+    }
+}
+
+/// Applies the host's new scale factor to the primary window. Bevy UI follows the window's scale
+/// factor, and the host is asked to resize the window since its physical size has changed. If the
+/// host refuses, the previous scale factor is restored.
+fn scale_factor_system(
+    ctx: Res<NIHContext>,
+    bevy_state: Res<NIHBevyState>,
+    pending: Res<NIHScaleFactor>,
+    mut scale_events: EventWriter<ScaleFactorChanged>,
+    mut rejected_events: EventWriter<ScaleFactorRejected>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Some(scale_factor) = pending.0.take() else {
+        return;
+    };
+    let Ok(mut window) = q_window.get_single_mut() else {
+        return;
+    };
+
+    let old_scale_factor = window.resolution.scale_factor();
+    window
+        .resolution
+        .set_scale_factor_and_apply_to_physical_size(scale_factor);
+    resize_window(&bevy_state.0, &mut q_window);
+
+    if ctx.context().request_resize() {
+        scale_events.send(ScaleFactorChanged { scale_factor });
+    } else {
+        if let Ok(mut window) = q_window.get_single_mut() {
+            window
+                .resolution
+                .set_scale_factor_and_apply_to_physical_size(old_scale_factor);
+        }
+        rejected_events.send(ScaleFactorRejected { scale_factor });
+    }
+}
+
+fn resize_system(
+    ctx: Res<NIHContext>,
+    bevy_state: Res<NIHBevyState>,
+    mut resize_requests: EventReader<ResizeRequest>,
+    mut rejected_events: EventWriter<ResizeRejected>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Some(request) = resize_requests.read().last() else {
+        return;
+    };
+
+    let new_size = bevy_state.0.clamp_size((request.width, request.height));
+    let old_size = bevy_state.0.size();
+    if new_size == old_size {
+        return;
+    }
+
+    // The host will query the editor's size after we request the resize, so the new size needs to
+    // be stored first
+    bevy_state.0.size.store(new_size);
+    if ctx.context().request_resize() {
+        resize_window(&bevy_state.0, &mut q_window);
+    } else {
+        bevy_state.0.size.store(old_size);
+        rejected_events.send(ResizeRejected {
+            width: new_size.0,
+            height: new_size.1,
+        });
+    }
+}
+
+fn zoom_system(
+    ctx: Res<NIHContext>,
+    bevy_state: Res<NIHBevyState>,
+    ui_scale: Option<ResMut<UiScale>>,
+    mut zoom_requests: EventReader<ZoomRequest>,
+    mut rejected_events: EventWriter<ZoomRejected>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Some(request) = zoom_requests.read().last() else {
+        return;
+    };
+
+    let old_zoom = bevy_state.0.zoom();
+    bevy_state.0.set_zoom(request.zoom);
+    let new_zoom = bevy_state.0.zoom();
+    if new_zoom == old_zoom {
+        return;
+    }
+
+    if ctx.context().request_resize() {
+        resize_window(&bevy_state.0, &mut q_window);
+        if let Some(mut ui_scale) = ui_scale {
+            ui_scale.0 = new_zoom;
+        }
+    } else {
+        bevy_state.0.set_zoom(old_zoom);
+        rejected_events.send(ZoomRejected { zoom: new_zoom });
+    }
+}
+
+/// Apply the state's zoomed size to the primary window. bevy_baseview applies the window's new
+/// resolution to the baseview window.
+fn resize_window(bevy_state: &BevyState, q_window: &mut Query<&mut Window, With<PrimaryWindow>>) {
+    let (width, height) = bevy_state.scaled_size();
+    if let Ok(mut window) = q_window.get_single_mut() {
+        window.resolution.set(width as f32, height as f32);
+    }
+}